@@ -0,0 +1,149 @@
+use crate::epidemic::{accumulate, infect, infectious, pick, susceptibility, DoseResponse, Route};
+use crate::field::Grid;
+use crate::scenario::Scenario;
+use crate::strain::Strains;
+use crate::vec2::V;
+use crate::{Dot, State};
+use rand::Rng;
+use rand_distr::Bernoulli;
+use serde::{Deserialize, Serialize};
+
+/// Airborne transmission through a concentration field emitted by the infectious dots.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Aerosol {
+    pub cell: f64,
+    // emitted per second by each infectious dot
+    pub emission: f64,
+    pub diffusion: f64,
+    // removal rate per second by ventilation and inactivation
+    pub decay: f64,
+    // volume inhaled per second, the inhaled dose is `breathing * concentration * dt`
+    pub breathing: f64,
+    pub response: DoseResponse,
+    // rectangles given by two opposite corners
+    pub walls: Vec<(V, V)>,
+}
+
+impl Default for Aerosol {
+    fn default() -> Aerosol {
+        Aerosol {
+            cell: 0.25,
+            emission: 1.0,
+            diffusion: 0.05,
+            decay: 0.1,
+            breathing: 0.05,
+            response: DoseResponse::Exponential { scale: 1.0 },
+            walls: Vec::new(),
+        }
+    }
+}
+
+impl Aerosol {
+    pub fn grid(&self, radius: f64) -> Grid {
+        Grid::new(radius, self.cell, &self.walls)
+    }
+}
+
+/// Emission, transport and inhalation during the last `dt` seconds, `air` has one layer per
+/// strain.
+#[allow(clippy::too_many_arguments)]
+pub fn aerosol<R: Rng>(
+    air: &mut Vec<Grid>,
+    dots: &mut [Dot],
+    strains: &mut Strains,
+    scenario: &Scenario,
+    config: &Aerosol,
+    t: f64,
+    dt: f64,
+    rng: &mut R,
+) {
+    while air.len() < strains.len() {
+        air.push(config.grid(scenario.radius));
+    }
+
+    for a in dots.iter() {
+        if infectious(a.state) {
+            let emission = config.emission * strains.list[a.strain].transmissibility;
+            air[a.strain].deposit(a.pos(t), emission * dt);
+        }
+    }
+    for grid in air.iter_mut() {
+        grid.diffuse(config.diffusion, dt);
+        grid.decay(config.decay, dt);
+    }
+
+    for a in dots.iter_mut() {
+        if !matches!(a.state, State::Susceptible | State::Recovered(_)) {
+            continue;
+        }
+        let mut weights = Vec::new();
+        for (s, grid) in air.iter().enumerate() {
+            let c = grid.get(a.pos(t));
+            if c > 0.0 {
+                let w = config.breathing * c * dt * susceptibility(a, strains, scenario, s);
+                accumulate(&mut weights, s, w);
+            }
+        }
+        let increment: f64 = weights.iter().map(|(_, w)| w).sum();
+        if increment > 0.0 {
+            let p = config.response.conditional(a.inhaled, increment);
+            a.inhaled += increment;
+            if rng.sample(Bernoulli::new(p).unwrap()) {
+                let s = pick(&weights, rng);
+                infect(a, strains, s, scenario, t, Route::Aerosol, rng);
+            }
+        }
+    }
+}
+
+#[test]
+fn emission_and_decay() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let scenario = Scenario::default();
+    let config = Aerosol {
+        emission: 100.0,
+        breathing: 10.0,
+        ..Aerosol::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut strains = Strains::new(&scenario.strains);
+    let mut air = Vec::new();
+    // an infectious dot and a susceptible one in the same cell
+    let mut dots = vec![Dot::new(V::new(0.0, 0.0)), Dot::new(V::new(0.1, 0.0))];
+    dots[0].state = State::Infected(100.0);
+
+    aerosol(
+        &mut air,
+        &mut dots,
+        &mut strains,
+        &scenario,
+        &config,
+        1.0,
+        1.0,
+        &mut rng,
+    );
+    assert_eq!(air.len(), 1);
+    assert!(matches!(dots[1].state, State::Asymptomatic(_)));
+    assert_eq!(dots[1].route, Some(Route::Aerosol));
+
+    // without emitters the air is only ventilated, diffusion keeps the total
+    for a in dots.iter_mut() {
+        a.state = State::Dead;
+    }
+    let before = air[0].total();
+    aerosol(
+        &mut air,
+        &mut dots,
+        &mut strains,
+        &scenario,
+        &config,
+        2.0,
+        1.0,
+        &mut rng,
+    );
+    let expected = before * (-config.decay).exp();
+    assert!((air[0].total() - expected).abs() < 1e-9 * before);
+}
@@ -0,0 +1,147 @@
+use crate::scenario::Scenario;
+use crate::{Dot, State};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Perception {
+    // fraction of the living dots with symptoms
+    Global,
+    // fraction of the other living dots with symptoms within `range`
+    Local { range: f64 },
+}
+
+/// The avoidance of the dots with symptoms follows the perceived prevalence and wears off with
+/// fatigue.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Awareness {
+    pub perception: Perception,
+    // prevalence at which the dots are half aware
+    pub half_prevalence: f64,
+    // the repulsion from the dots with symptoms is multiplied by `baseline + strength * awareness`
+    pub baseline: f64,
+    pub strength: f64,
+    // fatigue grows in `fatigue_time` seconds of full awareness and recovers in `recovery_time`
+    pub fatigue_time: f64,
+    pub recovery_time: f64,
+}
+
+impl Default for Awareness {
+    fn default() -> Awareness {
+        Awareness {
+            perception: Perception::Global,
+            half_prevalence: 0.05,
+            baseline: 1.0,
+            strength: 1.0,
+            fatigue_time: 60.0,
+            recovery_time: 30.0,
+        }
+    }
+}
+
+fn symptomatic(a: &Dot) -> bool {
+    matches!(a.state, State::Infected(_) | State::Healed(_))
+}
+
+/// Multiplier of the pair energy felt by `a` because of `b`.
+pub fn weight(a: &Dot, b: &Dot, scenario: &Scenario) -> f64 {
+    match &scenario.awareness {
+        Some(config) if symptomatic(b) && !symptomatic(a) => {
+            config.baseline + config.strength * a.awareness
+        }
+        _ => 1.0,
+    }
+}
+
+/// Updates the awareness and fatigue of every dot after `dt` seconds.
+pub fn update(dots: &mut [Dot], config: &Awareness, dt: f64) {
+    let alive = |a: &Dot| a.state != State::Dead;
+
+    let prevalence: Vec<f64> = match config.perception {
+        Perception::Global => {
+            let n = dots.iter().filter(|a| alive(a)).count();
+            let sick = dots.iter().filter(|a| symptomatic(a)).count();
+            let p = if n > 0 { sick as f64 / n as f64 } else { 0.0 };
+            vec![p; dots.len()]
+        }
+        Perception::Local { range } => {
+            dots.iter()
+                .enumerate()
+                .map(|(i, a)| {
+                    let near = dots.iter().enumerate().filter(|&(j, b)| {
+                        j != i && alive(b) && (a.new_pos - b.new_pos).norm() < range
+                    });
+                    let (n, sick) = near.fold((0, 0), |(n, s), (_, b)| {
+                        (n + 1, s + symptomatic(b) as usize)
+                    });
+                    if n > 0 {
+                        sick as f64 / n as f64
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        }
+    };
+
+    for (a, p) in dots.iter_mut().zip(prevalence) {
+        let raw = p / (p + config.half_prevalence);
+        a.awareness = raw * (1.0 - a.fatigue);
+        a.fatigue += dt * (a.awareness / config.fatigue_time - a.fatigue / config.recovery_time);
+        a.fatigue = a.fatigue.clamp(0.0, 1.0);
+    }
+}
+
+#[test]
+fn fatigue() {
+    use crate::montecarlo::energy;
+    use crate::vec2::V;
+
+    let config = Awareness::default();
+    let mut dots: Vec<Dot> = (0..10).map(|_| Dot::new(V::new(0.0, 0.0))).collect();
+    dots[0].state = State::Infected(1e9);
+
+    update(&mut dots, &config, 0.1);
+    let first = dots[1].awareness;
+    assert!(first > 0.6);
+
+    for _ in 0..1000 {
+        update(&mut dots, &config, 0.1);
+    }
+    // a constant prevalence is taken less and less seriously
+    assert!(dots[1].awareness < 0.8 * first);
+    assert!(dots[1].fatigue > 0.0);
+
+    let scenario = Scenario {
+        awareness: Some(config),
+        ..Scenario::default()
+    };
+    assert_eq!(weight(&dots[0], &dots[1], &scenario), 1.0);
+    assert_eq!(
+        weight(&dots[1], &dots[0], &scenario),
+        1.0 + dots[1].awareness
+    );
+
+    // the total energy counts the weighted share of both dots of a pair
+    let mut pair = vec![dots[0].clone(), dots[1].clone()];
+    pair[1].new_pos = V::new(0.05, 0.0);
+    let aware = Scenario {
+        global_strength: 0.0,
+        ..scenario
+    };
+    let unaware = Scenario {
+        awareness: None,
+        ..aware.clone()
+    };
+    let ratio = energy(&pair, &aware) / energy(&pair, &unaware);
+    assert!((ratio - (2.0 + pair[1].awareness) / 2.0).abs() < 1e-12);
+
+    // a dot does not perceive itself
+    let local = Awareness {
+        perception: Perception::Local { range: 1.0 },
+        ..config
+    };
+    let mut two = vec![dots[0].clone(), Dot::new(V::new(0.0, 0.0))];
+    update(&mut two, &local, 0.1);
+    assert_eq!(two[0].awareness, 0.0);
+}
@@ -0,0 +1,213 @@
+use crate::ensemble::{parallel, quantile, run, Ensemble, Run};
+use crate::invalid;
+use crate::scenario::Scenario;
+use crate::sweep::{Design, Range};
+use rand::Rng;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+/// Rejection ABC: the parameters are drawn from uniform priors over their ranges and the draws
+/// whose daily cases are the closest to the observed ones are kept.
+#[derive(Clone, Copy)]
+pub struct Abc {
+    // draws from the prior, each is simulated once
+    pub samples: usize,
+    // fraction of the draws kept as posterior samples
+    pub accept: f64,
+    // duration of a day in seconds
+    pub day: f64,
+}
+
+impl Default for Abc {
+    fn default() -> Abc {
+        Abc {
+            samples: 200,
+            accept: 0.1,
+            day: 1.0,
+        }
+    }
+}
+
+/// Daily cases from lines `day,cases`, after an optional header on the first line. The days
+/// are integers and the ones missing between the first and the last had no case.
+pub fn read_cases(path: &str) -> io::Result<Vec<f64>> {
+    let mut x: Vec<(i64, f64)> = Vec::new();
+    let text = fs::read_to_string(path)?;
+    for (k, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut it = line.split(',').map(|s| s.trim().parse::<f64>());
+        let (day, cases) = match (it.next(), it.next()) {
+            (Some(Ok(day)), Some(Ok(cases))) => (day, cases),
+            _ if k == 0 => continue,
+            _ => return Err(invalid(format!("line {}: bad row {}", k + 1, line))),
+        };
+        if !day.is_finite() || day.fract() != 0.0 {
+            return Err(invalid(format!("bad day {}", day)));
+        }
+        if !cases.is_finite() {
+            return Err(invalid(format!("bad cases {} on day {}", cases, day)));
+        }
+        x.push((day as i64, cases));
+    }
+    x.sort_by_key(|&(day, _)| day);
+
+    let first = match x.first() {
+        Some(&(day, _)) => day,
+        None => return Err(invalid("no observed cases")),
+    };
+    let mut cases = vec![None; (x[x.len() - 1].0 - first) as usize + 1];
+    for (day, c) in x {
+        let k = (day - first) as usize;
+        if cases[k].is_some() {
+            return Err(invalid(format!("day {} appears twice", day)));
+        }
+        cases[k] = Some(c);
+    }
+    Ok(cases.into_iter().map(|c| c.unwrap_or(0.0)).collect())
+}
+
+/// Onsets of symptoms during each of the first `days` days of the run.
+pub fn daily(run: &Run, day: f64, days: usize) -> Vec<f64> {
+    let mut x = Vec::with_capacity(days);
+    let mut last = 0;
+    let mut k = 0;
+    for d in 1..=days {
+        // small tolerance against the rounding of the step times
+        while k < run.t.len() && run.t[k] <= d as f64 * day + 1e-9 {
+            k += 1;
+        }
+        let cumulative = if k > 0 { run.cases[k - 1] } else { 0 };
+        x.push((cumulative - last) as f64);
+        last = cumulative;
+    }
+    x
+}
+
+/// Root mean square difference of the daily cases.
+pub fn distance(simulated: &[f64], observed: &[f64]) -> f64 {
+    let n = observed.len().max(1) as f64;
+    let s: f64 = simulated
+        .iter()
+        .zip(observed)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum();
+    (s / n).sqrt()
+}
+
+pub struct Sample {
+    pub values: Vec<f64>,
+    pub seed: u64,
+    pub distance: f64,
+}
+
+/// Posterior samples, closest first. `config.duration` is replaced by the length of the
+/// observations.
+pub fn abc<R: Rng>(
+    scenario: &Scenario,
+    ranges: &[Range],
+    observed: &[f64],
+    abc: &Abc,
+    config: &Ensemble,
+    rng: &mut R,
+) -> Vec<Sample> {
+    let config = Ensemble {
+        duration: observed.len() as f64 * abc.day,
+        ..config.clone()
+    };
+    let prior = Design::LatinHypercube {
+        samples: abc.samples,
+    }
+    .points(ranges.len(), rng);
+
+    let mut samples = parallel(prior.len(), |i| {
+        let mut s = scenario.clone();
+        let values: Vec<f64> = ranges
+            .iter()
+            .zip(&prior[i])
+            .map(|(r, &u)| r.value(u))
+            .collect();
+        for (r, &x) in ranges.iter().zip(values.iter()) {
+            r.parameter.apply(&mut s, x);
+        }
+        let seed = config.seed + i as u64;
+        let x = run(&s, &config, seed);
+        let distance = distance(&daily(&x, abc.day, observed.len()), observed);
+        Sample {
+            values,
+            seed,
+            distance,
+        }
+    });
+
+    samples.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+    let n = (abc.accept * samples.len() as f64).ceil() as usize;
+    samples.truncate(n.max(1));
+    samples
+}
+
+pub fn write_posterior(path: &str, ranges: &[Range], samples: &[Sample]) -> io::Result<()> {
+    let mut csv = BufWriter::new(File::create(path)?);
+    write!(csv, "seed")?;
+    for r in ranges {
+        write!(csv, ",{}", r.parameter.name())?;
+    }
+    writeln!(csv, ",distance")?;
+    for x in samples {
+        write!(csv, "{}", x.seed)?;
+        for v in x.values.iter() {
+            write!(csv, ",{}", v)?;
+        }
+        writeln!(csv, ",{}", x.distance)?;
+    }
+    csv.flush()
+}
+
+/// Posterior median and 90% interval of every parameter.
+pub fn summary(ranges: &[Range], samples: &[Sample]) -> String {
+    let mut s = String::new();
+    for (j, r) in ranges.iter().enumerate() {
+        let x: Vec<f64> = samples.iter().map(|x| x.values[j]).collect();
+        s += &format!(
+            "{}: {:.3} [{:.3}, {:.3}]\n",
+            r.parameter.name(),
+            quantile(&x, 0.5),
+            quantile(&x, 0.05),
+            quantile(&x, 0.95)
+        );
+    }
+    s
+}
+
+#[test]
+fn daily_cases() {
+    let run = Run {
+        seed: 0,
+        t: vec![0.5, 1.0, 1.5, 2.0, 2.5, 3.0],
+        counts: Vec::new(),
+        cases: vec![1, 1, 3, 4, 4, 9],
+        final_size: 0,
+        occupancy: Vec::new(),
+    };
+    assert_eq!(daily(&run, 1.0, 3), vec![1.0, 3.0, 5.0]);
+    assert_eq!(daily(&run, 2.0, 2), vec![4.0, 5.0]);
+    assert_eq!(distance(&[1.0, 3.0], &[2.0, 2.0]), 1.0);
+
+    let path = std::env::temp_dir().join("tom_daily_cases.csv");
+    let path = path.to_str().unwrap();
+    fs::write(path, "day,cases\n2,7\n1,3\n").unwrap();
+    assert_eq!(read_cases(path).unwrap(), vec![3.0, 7.0]);
+    // the missing day 3 had no case
+    fs::write(path, "day,cases\n4,2\n1,3\n2,7\n").unwrap();
+    assert_eq!(read_cases(path).unwrap(), vec![3.0, 7.0, 0.0, 2.0]);
+    fs::write(path, "day,cases\nNaN,2\n1,3\n").unwrap();
+    assert!(read_cases(path).is_err());
+    // only the first line can be a header
+    fs::write(path, "day,cases\n1,3\n2,x7\n").unwrap();
+    assert!(read_cases(path).is_err());
+    fs::write(path, "1,3\n2,7\n").unwrap();
+    assert_eq!(read_cases(path).unwrap(), vec![3.0, 7.0]);
+    fs::write(path, "day,cases\n").unwrap();
+    assert!(read_cases(path).is_err());
+}
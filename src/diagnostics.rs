@@ -0,0 +1,75 @@
+use crate::montecarlo::{energy, SweepStats};
+use crate::scenario::Scenario;
+use crate::Dot;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Statistics of the Metropolis sweeps, streamed to a csv file if one is given. The total energy
+/// after the sweep is only computed then, it costs a pass over every pair of dots.
+pub struct Diagnostics {
+    csv: Option<BufWriter<File>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics { csv: None }
+    }
+
+    pub fn with_csv(path: &str) -> io::Result<Diagnostics> {
+        let mut csv = BufWriter::new(File::create(path)?);
+        writeln!(csv, "t,proposed,accepted,acceptance,mean_step,energy")?;
+        Ok(Diagnostics { csv: Some(csv) })
+    }
+
+    pub fn record(&mut self, t: f64, stats: SweepStats, dots: &[Dot], scenario: &Scenario) {
+        if let Some(csv) = &mut self.csv {
+            let ok = writeln!(
+                csv,
+                "{},{},{},{},{},{}",
+                t,
+                stats.proposed,
+                stats.accepted,
+                stats.acceptance(),
+                stats.mean_step,
+                energy(dots, scenario)
+            )
+            .and_then(|_| csv.flush());
+            if let Err(e) = ok {
+                eprintln!("diagnostics: {}", e);
+                self.csv = None;
+            }
+        }
+    }
+}
+
+#[test]
+fn csv_columns() {
+    use crate::snapshot::SimRng;
+    use crate::world::World;
+    use rand::SeedableRng;
+
+    let scenario = Scenario {
+        radius: 1.0,
+        ..Scenario::default()
+    };
+    let mut rng = SimRng::seed_from_u64(0);
+    let mut world = World::new(&scenario, 10, &mut rng);
+    let path = std::env::temp_dir().join("tom_diagnostics.csv");
+    let mut diagnostics = Diagnostics::with_csv(path.to_str().unwrap()).unwrap();
+    for k in 0..3 {
+        let t = k as f64 * 0.3;
+        let stats = world.step(&scenario, t, 0.3, &mut rng);
+        diagnostics.record(t, stats, &world.dots, &scenario);
+    }
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "t,proposed,accepted,acceptance,mean_step,energy");
+    assert_eq!(lines.len(), 4);
+    let x: Vec<f64> = lines[3].split(',').map(|v| v.parse().unwrap()).collect();
+    assert_eq!(x[0], 0.6);
+    assert_eq!(x[1], 10.0);
+    assert!(x[2] > 0.0);
+    assert_eq!(x[3], x[2] / x[1]);
+    assert_eq!(x[5], energy(&world.dots, &scenario));
+}
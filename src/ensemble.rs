@@ -0,0 +1,297 @@
+use crate::hospital;
+use crate::population::{self, Agent};
+use crate::scenario::Scenario;
+use crate::trajectory::{self, Trajectory};
+use crate::world::World;
+use crate::{Compartment, State};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Independent headless runs of the same scenario, run `i` is seeded with `seed + i`.
+#[derive(Clone)]
+pub struct Ensemble {
+    pub runs: usize,
+    pub dots: usize,
+    // initial dots of every run instead of `dots` random ones, checked before the runs
+    pub population: Option<Vec<Agent>>,
+    // simulated seconds per run
+    pub duration: f64,
+    pub dt: f64,
+    pub seed: u64,
+}
+
+impl Default for Ensemble {
+    fn default() -> Ensemble {
+        Ensemble {
+            runs: 20,
+            dots: 1400,
+            population: None,
+            duration: 100.0,
+            dt: crate::STEP,
+            seed: 0,
+        }
+    }
+}
+
+/// Compartment counts of a single run, recorded after every step.
+pub struct Run {
+    pub seed: u64,
+    pub t: Vec<f64>,
+    pub counts: Vec<[usize; Compartment::COUNT]>,
+    // cumulative number of onsets of symptoms
+    pub cases: Vec<usize>,
+    // dots infected at least once
+    pub final_size: usize,
+    // occupied beds and severe cases waiting for one, empty without a hospital
+    pub occupancy: Vec<(usize, usize)>,
+}
+
+impl Run {
+    fn infectious(x: &[usize; Compartment::COUNT]) -> usize {
+        x[Compartment::Asymptomatic as usize] + x[Compartment::Infected as usize]
+    }
+
+    /// Time and height of the largest number of infectious dots, the first one on ties.
+    pub fn peak(&self) -> (f64, usize) {
+        let mut best = (0.0, 0);
+        for (t, x) in self.t.iter().zip(self.counts.iter()) {
+            if Run::infectious(x) > best.1 {
+                best = (*t, Run::infectious(x));
+            }
+        }
+        best
+    }
+}
+
+pub fn run(scenario: &Scenario, config: &Ensemble, seed: u64) -> Run {
+    run_exported(scenario, config, seed, &mut None)
+}
+
+/// Same as `run`, with the dots written to the trajectory at the start and after every step.
+pub fn run_exported(
+    scenario: &Scenario,
+    config: &Ensemble,
+    seed: u64,
+    trajectory: &mut Option<Trajectory>,
+) -> Run {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut world = match &config.population {
+        Some(x) => {
+            let dots = population::dots(x, scenario, &mut rng).expect("invalid population");
+            World::with_dots(scenario, dots, &mut rng)
+        }
+        None => World::new(scenario, config.dots, &mut rng),
+    };
+    let steps = (config.duration / config.dt).ceil() as usize;
+
+    let mut x = Run {
+        seed,
+        t: Vec::with_capacity(steps),
+        counts: Vec::with_capacity(steps),
+        cases: Vec::with_capacity(steps),
+        final_size: 0,
+        occupancy: Vec::new(),
+    };
+    trajectory::export(trajectory, 0.0, &world.dots);
+    for k in 0..steps {
+        let t = k as f64 * config.dt;
+        world.step(scenario, t, config.dt, &mut rng);
+        trajectory::export(trajectory, t + config.dt, &world.dots);
+        x.t.push(t + config.dt);
+        x.counts.push(world.counts());
+        x.cases.push(world.cases);
+        if scenario.hospital.is_some() {
+            x.occupancy.push(hospital::occupancy(&world.dots));
+        }
+    }
+    x.final_size = world
+        .dots
+        .iter()
+        .filter(|a| a.state != State::Susceptible || !a.immunity.is_empty())
+        .count();
+    x
+}
+
+/// `f(0), ..., f(n - 1)` computed on every available core.
+pub fn parallel<T: Send, F: Fn(usize) -> T + Sync>(n: usize, f: F) -> Vec<T> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let next = AtomicUsize::new(0);
+    let done = Mutex::new(Vec::new());
+
+    thread::scope(|s| {
+        for _ in 0..threads.min(n) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= n {
+                    break;
+                }
+                let x = f(i);
+                done.lock().unwrap().push((i, x));
+            });
+        }
+    });
+
+    let mut x = done.into_inner().unwrap();
+    x.sort_by_key(|(i, _)| *i);
+    x.into_iter().map(|(_, x)| x).collect()
+}
+
+/// Runs the ensemble on every available core, the runs are returned in the order of their seeds.
+/// The run with the first seed is exported to the trajectory if one is given.
+pub fn ensemble(
+    scenario: &Scenario,
+    config: &Ensemble,
+    trajectory: Option<Trajectory>,
+) -> Vec<Run> {
+    let trajectory = Mutex::new(trajectory);
+    parallel(config.runs, |i| {
+        let mut x = match i {
+            0 => trajectory.lock().unwrap().take(),
+            _ => None,
+        };
+        run_exported(scenario, config, config.seed + i as u64, &mut x)
+    })
+}
+
+/// Quantile `q` of the values, interpolated linearly between the order statistics.
+pub fn quantile(x: &[f64], q: f64) -> f64 {
+    if x.is_empty() {
+        return f64::NAN;
+    }
+    let mut x = x.to_vec();
+    x.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let h = q.clamp(0.0, 1.0) * (x.len() - 1) as f64;
+    let i = h.floor() as usize;
+    let j = (i + 1).min(x.len() - 1);
+    x[i] + (h - i as f64) * (x[j] - x[i])
+}
+
+/// One line per time with the quantiles of every compartment over the runs.
+pub fn write_bands(path: &str, runs: &[Run], quantiles: &[f64]) -> io::Result<()> {
+    let mut csv = BufWriter::new(File::create(path)?);
+    write!(csv, "t")?;
+    for c in Compartment::ALL.iter() {
+        for q in quantiles {
+            write!(csv, ",{:?}_q{}", c, q)?;
+        }
+    }
+    writeln!(csv)?;
+
+    let steps = runs.iter().map(|x| x.t.len()).min().unwrap_or(0);
+    for k in 0..steps {
+        write!(csv, "{}", runs[0].t[k])?;
+        for c in 0..Compartment::COUNT {
+            let x: Vec<f64> = runs.iter().map(|r| r.counts[k][c] as f64).collect();
+            for &q in quantiles {
+                write!(csv, ",{}", quantile(&x, q))?;
+            }
+        }
+        writeln!(csv)?;
+    }
+    csv.flush()
+}
+
+/// One line per run with its final size, peak time and peak height.
+pub fn write_runs(path: &str, runs: &[Run]) -> io::Result<()> {
+    let mut csv = BufWriter::new(File::create(path)?);
+    writeln!(csv, "seed,final_size,peak_time,peak_height")?;
+    for x in runs {
+        let (t, h) = x.peak();
+        writeln!(csv, "{},{},{},{}", x.seed, x.final_size, t, h)?;
+    }
+    csv.flush()
+}
+
+/// Occupancy of the hospital after every step of every run.
+pub fn write_occupancy(path: &str, runs: &[Run]) -> io::Result<()> {
+    let mut csv = BufWriter::new(File::create(path)?);
+    writeln!(csv, "seed,t,admitted,waiting")?;
+    for x in runs {
+        for (t, (admitted, waiting)) in x.t.iter().zip(x.occupancy.iter()) {
+            writeln!(csv, "{},{},{},{}", x.seed, t, admitted, waiting)?;
+        }
+    }
+    csv.flush()
+}
+
+/// Median and 90% interval of the final size, peak time and peak height.
+pub fn summary(runs: &[Run]) -> String {
+    let line = |name: &str, x: Vec<f64>| {
+        format!(
+            "{}: {:.1} [{:.1}, {:.1}]\n",
+            name,
+            quantile(&x, 0.5),
+            quantile(&x, 0.05),
+            quantile(&x, 0.95)
+        )
+    };
+    let mut s = String::new();
+    s += &line(
+        "final size",
+        runs.iter().map(|x| x.final_size as f64).collect(),
+    );
+    s += &line("peak time", runs.iter().map(|x| x.peak().0).collect());
+    s += &line(
+        "peak height",
+        runs.iter().map(|x| x.peak().1 as f64).collect(),
+    );
+    s
+}
+
+#[test]
+fn reproducible_runs() {
+    assert_eq!(quantile(&[3.0, 1.0, 2.0, 4.0], 0.5), 2.5);
+    assert_eq!(quantile(&[3.0, 1.0, 2.0], 1.0), 3.0);
+
+    let scenario = Scenario {
+        radius: 1.0,
+        ..Scenario::default()
+    };
+    let config = Ensemble {
+        runs: 3,
+        dots: 50,
+        duration: 6.0,
+        ..Ensemble::default()
+    };
+    let runs = ensemble(&scenario, &config, None);
+    assert_eq!(runs.len(), 3);
+    for x in runs.iter() {
+        assert_eq!(x.counts.len(), 20);
+        assert!(x.counts.iter().all(|c| c.iter().sum::<usize>() == 50));
+        assert!(x.final_size >= 1);
+    }
+    // a run only depends on its seed, not on the thread it ran in
+    let again = run(&scenario, &config, 1);
+    assert_eq!(again.counts, runs[1].counts);
+
+    // the dots of the population replace the random ones
+    let agents = vec![
+        Agent {
+            x: 0.5,
+            state: Some("Infected".to_string()),
+            ..Agent::default()
+        },
+        Agent::default(),
+    ];
+    let config = Ensemble {
+        population: Some(agents),
+        ..config
+    };
+    let x = run(&scenario, &config, 0);
+    assert!(x.counts.iter().all(|c| c.iter().sum::<usize>() == 2));
+    assert!(x.final_size >= 1);
+    assert!(x.occupancy.is_empty());
+
+    // the occupancy is kept after every step when there is a hospital
+    let scenario = Scenario {
+        hospital: Some(crate::hospital::Hospital::default()),
+        ..scenario
+    };
+    let x = run(&scenario, &config, 0);
+    assert_eq!(x.occupancy.len(), x.t.len());
+}
@@ -0,0 +1,334 @@
+use crate::hospital::Care;
+use crate::scenario::Scenario;
+use crate::strain::Strains;
+use crate::{Dot, State};
+use rand::Rng;
+use rand_distr::{Bernoulli, Exp, Gamma};
+use serde::{Deserialize, Serialize};
+
+/// How proximity to infectious dots turns into infections.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Transmission {
+    // infected as soon as an infectious dot is within the contact range
+    Contact,
+    // time spent within range, weighted by `1 - r / range`, is accumulated into a dose
+    Dose(DoseResponse),
+}
+
+/// Probability of having been infected after receiving a given dose.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum DoseResponse {
+    // 1 - exp(-dose / scale)
+    Exponential { scale: f64 },
+    // dose^n / (d50^n + dose^n)
+    Hill { d50: f64, n: f64 },
+}
+
+impl DoseResponse {
+    pub fn probability(&self, dose: f64) -> f64 {
+        match *self {
+            DoseResponse::Exponential { scale } => 1.0 - (-dose / scale).exp(),
+            DoseResponse::Hill { d50, n } => {
+                let x = (dose / d50).powf(n);
+                x / (1.0 + x)
+            }
+        }
+    }
+
+    // probability to get infected by the dose increment given that the previous dose did not infect
+    pub fn conditional(&self, dose: f64, increment: f64) -> f64 {
+        let p0 = self.probability(dose);
+        let p1 = self.probability(dose + increment);
+        if p0 >= 1.0 {
+            1.0
+        } else {
+            ((p1 - p0) / (1.0 - p0)).clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Distribution of the duration of the immunity after recovery.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Waning {
+    Never,
+    Fixed(f64),
+    Exponential { mean: f64 },
+    Gamma { mean: f64, shape: f64 },
+}
+
+impl Waning {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match *self {
+            Waning::Never => f64::INFINITY,
+            Waning::Fixed(duration) => duration,
+            Waning::Exponential { mean } => rng.sample(Exp::new(1.0 / mean).unwrap()),
+            Waning::Gamma { mean, shape } => rng.sample(Gamma::new(shape, mean / shape).unwrap()),
+        }
+    }
+}
+
+/// Pathway through which a dot got infected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Route {
+    Contact,
+    Aerosol,
+    Fomite,
+}
+
+pub fn infectious(state: State) -> bool {
+    matches!(state, State::Asymptomatic(_) | State::Infected(_))
+}
+
+/// Probability for `a` to catch `strain` if exposed, reduced by the immunity of past infections.
+pub fn susceptibility(a: &Dot, strains: &Strains, scenario: &Scenario, strain: usize) -> f64 {
+    match a.state {
+        State::Susceptible | State::Recovered(_) => {
+            let mut immunity = a
+                .immunity
+                .iter()
+                .map(|&s| {
+                    strains.cross_immunity(s, strain, &scenario.mutation, &scenario.cross_immunity)
+                })
+                .fold(0.0, f64::max);
+            if a.state == State::Susceptible {
+                // immunity has waned
+                immunity *= scenario.residual_immunity;
+            }
+            1.0 - immunity
+        }
+        _ => 0.0,
+    }
+}
+
+/// Infects `a` with `strain`, or with a new variant of it.
+pub fn infect<R: Rng>(
+    a: &mut Dot,
+    strains: &mut Strains,
+    strain: usize,
+    scenario: &Scenario,
+    t: f64,
+    route: Route,
+    rng: &mut R,
+) {
+    let mut strain = strain;
+    if rng.gen::<f64>() < scenario.mutation.probability {
+        strain = strains.mutate(strain, &scenario.mutation, rng);
+    }
+    a.state = State::Asymptomatic(t + scenario.incubation);
+    a.strain = strain;
+    a.route = Some(route);
+    a.dose = 0.0;
+    a.inhaled = 0.0;
+}
+
+/// Adds `w` to the weight of `strain`.
+pub fn accumulate(weights: &mut Vec<(usize, f64)>, strain: usize, w: f64) {
+    match weights.iter_mut().find(|(s, _)| *s == strain) {
+        Some((_, x)) => *x += w,
+        None => weights.push((strain, w)),
+    }
+}
+
+/// Draws a strain with probability proportional to its weight.
+pub fn pick<R: Rng>(weights: &[(usize, f64)], rng: &mut R) -> usize {
+    let total: f64 = weights.iter().map(|(_, w)| w).sum();
+    let mut x = rng.gen::<f64>() * total;
+    for &(s, w) in weights.iter() {
+        if x < w {
+            return s;
+        }
+        x -= w;
+    }
+    weights.last().unwrap().0
+}
+
+// the positions are sampled this many times per tick to integrate the exposure
+pub const EXPOSURE_SAMPLES: usize = 4;
+
+fn index_twice<T>(slc: &mut [T], a: usize, b: usize) -> Option<(&mut T, &mut T)> {
+    if a == b || a >= slc.len() || b >= slc.len() {
+        None
+    } else {
+        // safe because a, b are in bounds and distinct
+        unsafe {
+            let ar = &mut *(slc.get_unchecked_mut(a) as *mut _);
+            let br = &mut *(slc.get_unchecked_mut(b) as *mut _);
+            Some((ar, br))
+        }
+    }
+}
+
+fn contact<R: Rng>(
+    dots: &mut [Dot],
+    strains: &mut Strains,
+    scenario: &Scenario,
+    t: f64,
+    rng: &mut R,
+) {
+    for i in 0..dots.len() {
+        for j in i + 1..dots.len() {
+            let (a, b) = index_twice(dots, i, j).unwrap();
+            if (a.pos(t) - b.pos(t)).norm() < scenario.contact_range {
+                let (source, target) = if infectious(a.state) { (a, b) } else { (b, a) };
+                if !infectious(source.state) {
+                    continue;
+                }
+                let s = source.strain;
+                let p =
+                    strains.list[s].transmissibility * susceptibility(target, strains, scenario, s);
+                if p >= 1.0 || (p > 0.0 && rng.gen::<f64>() < p) {
+                    infect(target, strains, s, scenario, t, Route::Contact, rng);
+                }
+            }
+        }
+    }
+}
+
+fn dose<R: Rng>(
+    dots: &mut [Dot],
+    strains: &mut Strains,
+    scenario: &Scenario,
+    response: DoseResponse,
+    t: f64,
+    dt: f64,
+    rng: &mut R,
+) {
+    let range = scenario.contact_range;
+    let sources: Vec<usize> = (0..dots.len())
+        .filter(|&i| infectious(dots[i].state))
+        .collect();
+
+    let mut infected = Vec::new();
+    for i in 0..dots.len() {
+        if !matches!(dots[i].state, State::Susceptible | State::Recovered(_)) {
+            continue;
+        }
+
+        // midpoint rule over the last tick, weighted per strain
+        let mut weights = Vec::new();
+        for k in 0..EXPOSURE_SAMPLES {
+            let s = t - dt + (k as f64 + 0.5) * dt / EXPOSURE_SAMPLES as f64;
+            let x = dots[i].pos(s);
+            for &j in sources.iter() {
+                let r = (x - dots[j].pos(s)).norm();
+                if r < range {
+                    let w = (1.0 - r / range) * dt / EXPOSURE_SAMPLES as f64;
+                    accumulate(&mut weights, dots[j].strain, w);
+                }
+            }
+        }
+        for (s, w) in weights.iter_mut() {
+            *w *=
+                strains.list[*s].transmissibility * susceptibility(&dots[i], strains, scenario, *s);
+        }
+        let increment: f64 = weights.iter().map(|(_, w)| w).sum();
+
+        if increment > 0.0 {
+            let p = response.conditional(dots[i].dose, increment);
+            dots[i].dose += increment;
+            if rng.sample(Bernoulli::new(p).unwrap()) {
+                infected.push((i, pick(&weights, rng)));
+            }
+        }
+    }
+
+    for (i, s) in infected {
+        infect(&mut dots[i], strains, s, scenario, t, Route::Contact, rng);
+    }
+}
+
+/// Transmission during the last `dt` seconds followed by the progression of the disease.
+pub fn epidemic<R: Rng>(
+    dots: &mut [Dot],
+    strains: &mut Strains,
+    scenario: &Scenario,
+    t: f64,
+    dt: f64,
+    rng: &mut R,
+) {
+    match scenario.transmission {
+        Transmission::Contact => contact(dots, strains, scenario, t, rng),
+        Transmission::Dose(response) => dose(dots, strains, scenario, response, t, dt, rng),
+    }
+
+    for a in dots.iter_mut() {
+        match a.state {
+            State::Asymptomatic(end) if end < t => {
+                a.state = State::Infected(t + scenario.infectious_period);
+                if let Some(hospital) = &scenario.hospital {
+                    if rng.gen::<f64>() < hospital.severe_fraction {
+                        a.care = Care::Waiting;
+                    }
+                }
+            }
+            State::Infected(end) if end < t => {
+                let fatality = match &scenario.hospital {
+                    Some(hospital) => hospital.fatality(a, scenario.fatality),
+                    None => scenario.fatality,
+                };
+                let fatality = fatality * strains.list[a.strain].severity;
+                a.care = Care::None;
+                if rng.sample(Bernoulli::new(fatality.min(1.0)).unwrap()) {
+                    a.state = State::Dead;
+                } else {
+                    a.state = State::Recovered(t + scenario.waning.sample(rng));
+                    a.immunity.push(a.strain);
+                }
+            }
+            State::Healed(end) if end < t => {
+                a.state = State::Recovered(t + scenario.waning.sample(rng));
+                a.immunity.push(a.strain);
+            }
+            State::Recovered(end) if end < t => {
+                a.state = State::Susceptible;
+            }
+            _ => (),
+        }
+    }
+
+    if let Some(hospital) = &scenario.hospital {
+        hospital.admit(dots);
+    }
+}
+
+#[test]
+fn dose_response() {
+    let responses = [
+        DoseResponse::Exponential { scale: 2.0 },
+        DoseResponse::Hill { d50: 1.0, n: 3.0 },
+    ];
+    for response in responses.iter() {
+        // surviving two increments is the same as surviving their sum
+        let (d, a, b) = (0.3, 0.5, 0.7);
+        let survive = (1.0 - response.conditional(d, a)) * (1.0 - response.conditional(d + a, b));
+        let direct = 1.0 - response.conditional(d, a + b);
+        assert!((survive - direct).abs() < 1e-12);
+    }
+    assert!((DoseResponse::Hill { d50: 1.0, n: 2.0 }.probability(1.0) - 0.5).abs() < 1e-12);
+}
+
+#[test]
+fn waning_immunity() {
+    use crate::vec2::V;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let scenario = Scenario {
+        fatality: 0.0,
+        waning: Waning::Fixed(5.0),
+        ..Scenario::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut strains = Strains::new(&scenario.strains);
+    let mut dots = vec![Dot::new(V::new(0.0, 0.0))];
+    dots[0].state = State::Infected(1.0);
+
+    epidemic(&mut dots, &mut strains, &scenario, 1.5, 0.5, &mut rng);
+    assert!(dots[0].state == State::Recovered(6.5));
+    epidemic(&mut dots, &mut strains, &scenario, 6.0, 0.5, &mut rng);
+    assert!(dots[0].state == State::Recovered(6.5));
+    epidemic(&mut dots, &mut strains, &scenario, 7.0, 1.0, &mut rng);
+    assert!(dots[0].state == State::Susceptible);
+    // the past infection is remembered for the residual and cross immunity
+    assert_eq!(dots[0].immunity, vec![0]);
+}
@@ -0,0 +1,137 @@
+use crate::epidemic::{
+    accumulate, infect, infectious, pick, susceptibility, Route, EXPOSURE_SAMPLES,
+};
+use crate::field::Grid;
+use crate::scenario::Scenario;
+use crate::strain::Strains;
+use crate::vec2::V;
+use crate::{Dot, State};
+use rand::Rng;
+use rand_distr::Bernoulli;
+use serde::{Deserialize, Serialize};
+
+/// Indirect transmission through the contaminated surfaces of the cells visited by the dots.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fomite {
+    pub cell: f64,
+    // deposited per second by each infectious dot in the cell it stands on
+    pub shedding: f64,
+    // contamination decays as exp(-decay t)
+    pub decay: f64,
+    // infection rate per second and per unit of contamination of the cell
+    pub pickup: f64,
+}
+
+impl Default for Fomite {
+    fn default() -> Fomite {
+        Fomite {
+            cell: 0.1,
+            shedding: 1.0,
+            decay: 0.05,
+            pickup: 0.01,
+        }
+    }
+}
+
+impl Fomite {
+    pub fn grid(&self, radius: f64) -> Grid {
+        Grid::new(radius, self.cell, &[])
+    }
+}
+
+// positions visited during the last `dt` seconds
+fn path(a: &Dot, t: f64, dt: f64) -> Vec<V> {
+    let h = dt / EXPOSURE_SAMPLES as f64;
+    (0..EXPOSURE_SAMPLES)
+        .map(|k| a.pos(t - dt + (k as f64 + 0.5) * h))
+        .collect()
+}
+
+/// Deposition, decay and pickup during the last `dt` seconds, `surfaces` has one layer per
+/// strain.
+#[allow(clippy::too_many_arguments)]
+pub fn fomite<R: Rng>(
+    surfaces: &mut Vec<Grid>,
+    dots: &mut [Dot],
+    strains: &mut Strains,
+    scenario: &Scenario,
+    config: &Fomite,
+    t: f64,
+    dt: f64,
+    rng: &mut R,
+) {
+    let h = dt / EXPOSURE_SAMPLES as f64;
+    while surfaces.len() < strains.len() {
+        surfaces.push(config.grid(scenario.radius));
+    }
+
+    for a in dots.iter() {
+        if infectious(a.state) {
+            let shedding = config.shedding * strains.list[a.strain].transmissibility;
+            for x in path(a, t, dt) {
+                surfaces[a.strain].deposit(x, shedding * h);
+            }
+        }
+    }
+    for grid in surfaces.iter_mut() {
+        grid.decay(config.decay, dt);
+    }
+
+    for a in dots.iter_mut() {
+        if !matches!(a.state, State::Susceptible | State::Recovered(_)) {
+            continue;
+        }
+        let mut weights = Vec::new();
+        for x in path(a, t, dt) {
+            for (s, grid) in surfaces.iter().enumerate() {
+                accumulate(&mut weights, s, grid.get(x) * h);
+            }
+        }
+        for (s, w) in weights.iter_mut() {
+            *w *= config.pickup * susceptibility(a, strains, scenario, *s);
+        }
+        let exposure: f64 = weights.iter().map(|(_, w)| w).sum();
+        let p = 1.0 - (-exposure).exp();
+        if p > 0.0 && rng.sample(Bernoulli::new(p).unwrap()) {
+            let s = pick(&weights, rng);
+            infect(a, strains, s, scenario, t, Route::Fomite, rng);
+        }
+    }
+}
+
+#[test]
+fn contaminated_cell() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let scenario = Scenario::default();
+    let config = Fomite {
+        decay: 0.5,
+        pickup: 10.0,
+        ..Fomite::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut strains = Strains::new(&scenario.strains);
+    let mut surfaces = vec![config.grid(scenario.radius)];
+    surfaces[0].deposit(V::new(1.0, 1.0), 100.0);
+    // one dot touches the contaminated cell, the other one stays away
+    let mut dots = vec![Dot::new(V::new(1.0, 1.0)), Dot::new(V::new(-2.0, -2.0))];
+
+    fomite(
+        &mut surfaces,
+        &mut dots,
+        &mut strains,
+        &scenario,
+        &config,
+        1.0,
+        0.5,
+        &mut rng,
+    );
+    assert!(matches!(dots[0].state, State::Asymptomatic(_)));
+    assert_eq!(dots[0].route, Some(Route::Fomite));
+    assert!(dots[1].state == State::Susceptible);
+    // nothing was shed yet, the contamination only decayed
+    let expected = 100.0 * (-0.5f64 * 0.5).exp();
+    assert!((surfaces[0].total() - expected).abs() < 1e-9);
+}
@@ -0,0 +1,84 @@
+use crate::gl::math::{Mat4, Vec4};
+
+/// Drawing target of the scene: the window, an image in memory or an svg file. The shapes are
+/// given in the coordinates of the scene, mapped to the target by the view.
+pub trait Canvas {
+    fn set_view(&mut self, view: Mat4);
+    fn draw_circle(&mut self, x: f32, y: f32, r: f32, color: [f32; 3]);
+    // `(x, y)` is the corner with the smallest coordinates
+    fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 3]);
+
+    /// Many circles `(x, y, r, color)` at once, the window draws them in a single call.
+    fn draw_circles(&mut self, circles: &[(f32, f32, f32, [f32; 3])]) {
+        for &(x, y, r, color) in circles {
+            self.draw_circle(x, y, r, color);
+        }
+    }
+}
+
+/// Keeps the unit disk visible whatever the proportions of the target.
+pub fn projection(width: u32, height: u32) -> Mat4 {
+    let aspect_ratio = width as f32 / height as f32;
+    if aspect_ratio < 1.0 {
+        Mat4::diag(1.0, aspect_ratio, 1.0, 1.0)
+    } else {
+        Mat4::diag(1.0 / aspect_ratio, 1.0, 1.0, 1.0)
+    }
+}
+
+/// Pixel coordinates from the top left corner of a `width` by `height` target, for a point of the
+/// scene transformed by `m`, the projection times the view.
+pub fn to_pixel(m: Mat4, width: u32, height: u32, x: f32, y: f32) -> (f32, f32) {
+    let c = (m * Vec4::from_array([x, y, 0.0, 1.0])).as_array();
+    (
+        (c[0] + 1.0) / 2.0 * width as f32,
+        (1.0 - c[1]) / 2.0 * height as f32,
+    )
+}
+
+#[test]
+fn draw_world() {
+    use crate::gl::raster::Raster;
+    use crate::scenario::Scenario;
+    use crate::snapshot::SimRng;
+    use crate::world::World;
+    use rand::SeedableRng;
+
+    // counts the shapes instead of drawing them
+    #[derive(Default)]
+    struct Count {
+        circles: usize,
+        rects: usize,
+    }
+    impl Canvas for Count {
+        fn set_view(&mut self, _view: Mat4) {}
+        fn draw_circle(&mut self, _x: f32, _y: f32, _r: f32, _color: [f32; 3]) {
+            self.circles += 1;
+        }
+        fn draw_rect(&mut self, _x: f32, _y: f32, _w: f32, _h: f32, _color: [f32; 3]) {
+            self.rects += 1;
+        }
+    }
+
+    let scenario = Scenario::default();
+    let mut rng = SimRng::seed_from_u64(0);
+    let world = World::new(&scenario, 40, &mut rng);
+    let mut count = Count::default();
+    crate::draw_world(&mut count, &scenario, &world, 0.0);
+    assert_eq!(count.circles, 40);
+    // nothing in the air or on the surfaces yet
+    assert_eq!(count.rects, 0);
+    // one entry per strain, none for the asymptomatic dots drawn like the susceptible ones
+    let legend = crate::legend(&world.strains);
+    assert_eq!(legend.len(), 5);
+    assert_eq!(
+        legend[1],
+        ("Infected".to_string(), world.strains.list[0].color)
+    );
+
+    let mut raster = Raster::new(100, 100);
+    crate::draw_world(&mut raster, &scenario, &world, 0.0);
+    assert!(raster.rgb().iter().any(|&x| x > 0));
+    // nothing is drawn outside the disk
+    assert_eq!(&raster.rgb()[..3], &[0, 0, 0]);
+}
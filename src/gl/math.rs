@@ -151,7 +151,7 @@ impl Mat4 {
             [0.0, 0.0, -(2.0 * zfar * znear) / (zfar - znear), 0.0],
         ])
     }
-    pub fn to_mat3(&self) -> Mat3 {
+    pub fn to_mat3(self) -> Mat3 {
         Mat3([
             [self.0[0][0], self.0[0][1], self.0[0][2]],
             [self.0[1][0], self.0[1][1], self.0[1][2]],
@@ -193,10 +193,10 @@ impl ops::Mul for Mat4 {
 
     fn mul(self, _rhs: Mat4) -> Mat4 {
         let mut x = [[0.0f32; 4]; 4];
-        for i in 0..4 {
-            for j in 0..4 {
+        for (j, column) in x.iter_mut().enumerate() {
+            for (i, v) in column.iter_mut().enumerate() {
                 for k in 0..4 {
-                    x[j][i] += self.0[k][i] * _rhs.0[j][k];
+                    *v += self.0[k][i] * _rhs.0[j][k];
                 }
             }
         }
@@ -209,9 +209,9 @@ impl ops::Add for Mat3 {
 
     fn add(self, _rhs: Mat3) -> Mat3 {
         let mut x = [[0.0f32; 3]; 3];
-        for i in 0..3 {
-            for j in 0..3 {
-                x[j][i] += self.0[j][i] + _rhs.0[j][i];
+        for (j, column) in x.iter_mut().enumerate() {
+            for (i, v) in column.iter_mut().enumerate() {
+                *v += self.0[j][i] + _rhs.0[j][i];
             }
         }
         Mat3(x)
@@ -229,9 +229,9 @@ impl Vec3 {
 
     pub fn extern_prod(a: Vec3, b: Vec3) -> Mat3 {
         let mut x = [[0.0f32; 3]; 3];
-        for i in 0..3 {
-            for j in 0..3 {
-                x[j][i] = a.0[j] * b.0[i];
+        for (j, column) in x.iter_mut().enumerate() {
+            for (i, v) in column.iter_mut().enumerate() {
+                *v = a.0[j] * b.0[i];
             }
         }
         Mat3::from_array(x)
@@ -243,9 +243,9 @@ impl ops::Mul<Vec3> for Mat3 {
 
     fn mul(self, _rhs: Vec3) -> Vec3 {
         let mut x = [0.0f32; 3];
-        for i in 0..3 {
+        for (i, v) in x.iter_mut().enumerate() {
             for j in 0..3 {
-                x[i] += self.0[j][i] * _rhs.0[j];
+                *v += self.0[j][i] * _rhs.0[j];
             }
         }
         Vec3(x)
@@ -257,9 +257,9 @@ impl ops::Mul<Mat3> for Vec3 {
 
     fn mul(self, _rhs: Mat3) -> Vec3 {
         let mut x = [0.0f32; 3];
-        for i in 0..3 {
+        for (i, v) in x.iter_mut().enumerate() {
             for j in 0..3 {
-                x[i] += self.0[j] * _rhs.0[i][j];
+                *v += self.0[j] * _rhs.0[i][j];
             }
         }
         Vec3(x)
@@ -271,8 +271,8 @@ impl ops::Mul<Vec3> for f32 {
 
     fn mul(self, _rhs: Vec3) -> Vec3 {
         let mut x = [0.0f32; 3];
-        for i in 0..3 {
-            x[i] += self * _rhs.0[i];
+        for (v, r) in x.iter_mut().zip(_rhs.0.iter()) {
+            *v += self * r;
         }
         Vec3(x)
     }
@@ -292,9 +292,9 @@ impl ops::Mul<Vec4> for Mat4 {
 
     fn mul(self, _rhs: Vec4) -> Vec4 {
         let mut x = [0.0f32; 4];
-        for i in 0..4 {
+        for (i, v) in x.iter_mut().enumerate() {
             for j in 0..4 {
-                x[i] += self.0[j][i] * _rhs.0[j];
+                *v += self.0[j][i] * _rhs.0[j];
             }
         }
         Vec4(x)
@@ -1,2 +1,5 @@
+pub mod canvas;
 pub mod math;
-pub mod window;
\ No newline at end of file
+pub mod raster;
+pub mod svg;
+pub mod window;
@@ -0,0 +1,125 @@
+use crate::gl::canvas::{projection, to_pixel, Canvas};
+use crate::gl::math::{Mat4, Vec4};
+use std::fs::File;
+use std::io::{self, BufWriter};
+
+/// Software rasterizer implementing the `Canvas`, for the machines without a display.
+pub struct Raster {
+    pub width: u32,
+    pub height: u32,
+    pub view: Mat4,
+    proj: Mat4,
+    // rgb, row after row from the top
+    pixels: Vec<u8>,
+}
+
+impl Raster {
+    pub fn new(width: u32, height: u32) -> Raster {
+        Raster {
+            width,
+            height,
+            view: Mat4::identity(),
+            proj: projection(width, height),
+            pixels: vec![0; (width * height * 3) as usize],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.iter_mut().for_each(|x| *x = 0);
+    }
+
+    pub fn rgb(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    // fills the pixels whose center is inside the shape, `inside` takes coordinates of the scene
+    fn fill<F: Fn(f32, f32) -> bool>(
+        &mut self,
+        corners: [(f32, f32); 2],
+        color: [f32; 3],
+        inside: F,
+    ) {
+        let inverse = match (self.proj * self.view).inverse() {
+            Some(x) => x,
+            None => return,
+        };
+        let m = self.proj * self.view;
+        let a = to_pixel(m, self.width, self.height, corners[0].0, corners[0].1);
+        let b = to_pixel(m, self.width, self.height, corners[1].0, corners[1].1);
+        let clip = |v: f32, n: u32| v.max(0.0).min(n as f32) as u32;
+        let (x0, x1) = (
+            clip(a.0.min(b.0).floor(), self.width),
+            clip(a.0.max(b.0).ceil(), self.width),
+        );
+        let (y0, y1) = (
+            clip(a.1.min(b.1).floor(), self.height),
+            clip(a.1.max(b.1).ceil(), self.height),
+        );
+        let rgb = [
+            (color[0].clamp(0.0, 1.0) * 255.0).round() as u8,
+            (color[1].clamp(0.0, 1.0) * 255.0).round() as u8,
+            (color[2].clamp(0.0, 1.0) * 255.0).round() as u8,
+        ];
+
+        for j in y0..y1 {
+            for i in x0..x1 {
+                let cx = (i as f32 + 0.5) / self.width as f32 * 2.0 - 1.0;
+                let cy = 1.0 - (j as f32 + 0.5) / self.height as f32 * 2.0;
+                let p = (inverse * Vec4::from_array([cx, cy, 0.0, 1.0])).as_array();
+                if inside(p[0], p[1]) {
+                    let k = ((j * self.width + i) * 3) as usize;
+                    self.pixels[k..k + 3].copy_from_slice(&rgb);
+                }
+            }
+        }
+    }
+
+    pub fn save_png(&self, path: &str) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(io::Error::other)
+    }
+}
+
+impl Canvas for Raster {
+    fn set_view(&mut self, view: Mat4) {
+        self.view = view;
+    }
+
+    fn draw_circle(&mut self, x: f32, y: f32, r: f32, color: [f32; 3]) {
+        let corners = [(x - r, y - r), (x + r, y + r)];
+        self.fill(corners, color, |px, py| {
+            (px - x).powi(2) + (py - y).powi(2) <= r * r
+        });
+    }
+
+    fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 3]) {
+        let corners = [(x, y), (x + w, y + h)];
+        self.fill(corners, color, |px, py| {
+            px >= x.min(x + w) && px <= x.max(x + w) && py >= y.min(y + h) && py <= y.max(y + h)
+        });
+    }
+}
+
+#[test]
+fn rasterize() {
+    let mut x = Raster::new(20, 10);
+    x.view = Mat4::scale(0.5);
+    // the unit circle is a disk of radius 2.5 pixels in the middle
+    x.draw_circle(0.0, 0.0, 1.0, [1.0, 0.0, 0.0]);
+    let red = x.rgb().chunks(3).filter(|p| p == &[255, 0, 0]).count();
+    assert!((16..=24).contains(&red));
+    assert_eq!(&x.rgb()[(5 * 20 + 10) * 3..][..3], &[255, 0, 0]);
+    assert_eq!(&x.rgb()[..3], &[0, 0, 0]);
+
+    // the upper half of the view
+    x.clear();
+    x.draw_rect(-4.0, 0.0, 8.0, 2.0, [0.0, 0.0, 1.0]);
+    assert_eq!(x.rgb().chunks(3).filter(|p| p == &[0, 0, 255]).count(), 100);
+    assert_eq!(&x.rgb()[..3], &[0, 0, 255]);
+}
@@ -0,0 +1,124 @@
+use crate::gl::canvas::{projection, to_pixel, Canvas};
+use crate::gl::math::Mat4;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+
+/// Vector image implementing the `Canvas`, the shapes are kept as svg elements in pixel
+/// coordinates.
+pub struct Svg {
+    pub width: u32,
+    pub height: u32,
+    pub view: Mat4,
+    proj: Mat4,
+    elements: String,
+}
+
+// text of an element, `&` and `<` would otherwise be markup
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn rgb(color: [f32; 3]) -> String {
+    let c = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("rgb({},{},{})", c(color[0]), c(color[1]), c(color[2]))
+}
+
+impl Svg {
+    pub fn new(width: u32, height: u32) -> Svg {
+        Svg {
+            width,
+            height,
+            view: Mat4::identity(),
+            proj: projection(width, height),
+            elements: String::new(),
+        }
+    }
+
+    /// Colored dots and their labels in the upper left corner, above what is already drawn.
+    pub fn legend(&mut self, entries: &[(String, [f32; 3])]) {
+        for (k, (label, color)) in entries.iter().enumerate() {
+            let y = 20.0 + 20.0 * k as f32;
+            writeln!(
+                self.elements,
+                r#"<circle cx="20" cy="{}" r="5" fill="{}"/><text x="32" y="{}" fill="white" font-family="sans-serif" font-size="14" dominant-baseline="middle">{}</text>"#,
+                y,
+                rgb(*color),
+                y,
+                escape(label)
+            )
+            .unwrap();
+        }
+    }
+
+    pub fn document(&self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n<rect width=\"{w}\" height=\"{h}\" fill=\"black\"/>\n{}</svg>\n",
+            self.elements,
+            w = self.width,
+            h = self.height
+        )
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.document())
+    }
+}
+
+impl Canvas for Svg {
+    fn set_view(&mut self, view: Mat4) {
+        self.view = view;
+    }
+
+    fn draw_circle(&mut self, x: f32, y: f32, r: f32, color: [f32; 3]) {
+        let m = self.proj * self.view;
+        let c = to_pixel(m, self.width, self.height, x, y);
+        // the view and projection scale both axes the same way
+        let e = to_pixel(m, self.width, self.height, x + r, y);
+        let r = (e.0 - c.0).abs();
+        writeln!(
+            self.elements,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{:.2}" fill="{}"/>"#,
+            c.0,
+            c.1,
+            r,
+            rgb(color)
+        )
+        .unwrap();
+    }
+
+    fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 3]) {
+        let m = self.proj * self.view;
+        let a = to_pixel(m, self.width, self.height, x, y);
+        let b = to_pixel(m, self.width, self.height, x + w, y + h);
+        writeln!(
+            self.elements,
+            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}"/>"#,
+            a.0.min(b.0),
+            a.1.min(b.1),
+            (b.0 - a.0).abs(),
+            (b.1 - a.1).abs(),
+            rgb(color)
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn vector_image() {
+    let mut x = Svg::new(200, 100);
+    x.view = Mat4::scale(0.5);
+    x.draw_circle(0.0, 0.0, 1.0, [1.0, 0.0, 0.0]);
+    x.draw_rect(-4.0, 0.0, 8.0, 2.0, [0.0, 0.0, 1.0]);
+    x.legend(&[
+        ("Dead".to_string(), [1.0, 0.0, 1.0]),
+        ("a < b & c".to_string(), [0.0, 1.0, 0.0]),
+    ]);
+    let text = x.document();
+    assert!(text.contains(r#"<circle cx="100.00" cy="50.00" r="25.00" fill="rgb(255,0,0)"/>"#));
+    assert!(text.contains(r#"<rect x="0.00" y="0.00" width="200.00" height="50.00""#));
+    assert!(text.contains(">Dead</text>"));
+    assert!(text.contains(">a &lt; b &amp; c</text>"));
+}
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
+use crate::gl::canvas::{projection, Canvas};
 use crate::gl::math::{Mat4, Vec4};
+use glium::glutin;
 use glium::Surface;
 use std::collections::HashSet;
-use glium::glutin;
 
 #[derive(Clone, Copy)]
 struct Vertex {
@@ -17,6 +18,16 @@ impl Vertex {
 
 implement_vertex!(Vertex, position);
 
+// one circle of a batch
+#[derive(Clone, Copy)]
+struct Instance {
+    center: [f32; 2],
+    radius: f32,
+    instance_color: [f32; 3],
+}
+
+implement_vertex!(Instance, center, radius, instance_color);
+
 pub struct Painter<'a> {
     pub view: &'a mut Mat4,
     proj: Mat4,
@@ -24,10 +35,18 @@ pub struct Painter<'a> {
     circle_verticies: &'a glium::VertexBuffer<Vertex>,
     square_verticies: &'a glium::VertexBuffer<Vertex>,
     program: &'a glium::Program,
+    instanced_program: &'a glium::Program,
+    // kept from frame to frame, replaced by a larger one when a batch does not fit
+    instances: &'a mut Option<glium::VertexBuffer<Instance>>,
+    display: &'a glium::Display,
 }
 
-impl<'a> Painter<'a> {
-    pub fn draw_circle(&mut self, x: f32, y: f32, r: f32, color: [f32; 3]) {
+impl<'a> Canvas for Painter<'a> {
+    fn set_view(&mut self, view: Mat4) {
+        *self.view = view;
+    }
+
+    fn draw_circle(&mut self, x: f32, y: f32, r: f32, color: [f32; 3]) {
         let uniform = uniform! {
             model: (Mat4::translation(x, y, 0.0) * Mat4::scale(r)).as_array(),
             view: self.view.as_array(),
@@ -40,7 +59,7 @@ impl<'a> Painter<'a> {
         self.target
             .draw(
                 self.circle_verticies,
-                &glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan),
+                glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan),
                 self.program,
                 &uniform,
                 &params,
@@ -48,7 +67,7 @@ impl<'a> Painter<'a> {
             .unwrap();
     }
 
-    pub fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 3]) {
+    fn draw_rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 3]) {
         let uniform = uniform! {
             model: (Mat4::translation(x, y, 0.0) * Mat4::diag(w, h, 1.0, 1.0)).as_array(),
             view: self.view.as_array(),
@@ -61,26 +80,65 @@ impl<'a> Painter<'a> {
         self.target
             .draw(
                 self.square_verticies,
-                &glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan),
+                glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan),
                 self.program,
                 &uniform,
                 &params,
             )
             .unwrap();
     }
+
+    // the circles are written to one buffer and drawn in a single call
+    fn draw_circles(&mut self, circles: &[(f32, f32, f32, [f32; 3])]) {
+        if circles.is_empty() {
+            return;
+        }
+        let instances: Vec<Instance> = circles
+            .iter()
+            .map(|&(x, y, r, color)| Instance {
+                center: [x, y],
+                radius: r,
+                instance_color: color,
+            })
+            .collect();
+        let n = instances.len();
+        if self.instances.as_ref().is_none_or(|x| x.len() < n) {
+            let x = glium::VertexBuffer::empty_dynamic(self.display, n.next_power_of_two());
+            *self.instances = Some(x.unwrap());
+        }
+        let buffer = self.instances.as_mut().unwrap().slice(0..n).unwrap();
+        buffer.write(&instances);
+
+        let uniform = uniform! {
+            view: self.view.as_array(),
+            proj: self.proj.as_array(),
+        };
+
+        let params = Default::default();
+
+        self.target
+            .draw(
+                (self.circle_verticies, buffer.per_instance().unwrap()),
+                glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan),
+                self.instanced_program,
+                &uniform,
+                &params,
+            )
+            .unwrap();
+    }
 }
 
 pub fn animation<F>(mut draw: F) -> !
 where
-    F: 'static + FnMut(Painter, f64, Option<(f64, f64)>, bool, bool, Option<glutin::event::VirtualKeyCode>),
+    F: 'static
+        + FnMut(Painter, f64, Option<(f64, f64)>, bool, bool, Option<glutin::event::VirtualKeyCode>),
 {
     let event_loop = glutin::event_loop::EventLoop::new();
     let wb = glutin::window::WindowBuilder::new();
     let cb = glutin::ContextBuilder::new();
     let display = glium::Display::new(wb, cb, &event_loop).unwrap();
 
-    let mut circle_verticies = Vec::new();
-    circle_verticies.push(Vertex::new(0.0, 0.0));
+    let mut circle_verticies = vec![Vertex::new(0.0, 0.0)];
     {
         let n = 30;
         for i in 0..(n + 1) {
@@ -90,11 +148,12 @@ where
     }
     let circle_verticies = glium::VertexBuffer::new(&display, &circle_verticies).unwrap();
 
-    let mut square_verticies = Vec::new();
-    square_verticies.push(Vertex::new(0.0, 0.0));
-    square_verticies.push(Vertex::new(1.0, 0.0));
-    square_verticies.push(Vertex::new(1.0, 1.0));
-    square_verticies.push(Vertex::new(0.0, 1.0));
+    let square_verticies = vec![
+        Vertex::new(0.0, 0.0),
+        Vertex::new(1.0, 0.0),
+        Vertex::new(1.0, 1.0),
+        Vertex::new(0.0, 1.0),
+    ];
     let square_verticies = glium::VertexBuffer::new(&display, &square_verticies).unwrap();
 
     let vertex = r#"
@@ -124,6 +183,39 @@ where
 
     let program = glium::Program::from_source(&display, vertex, fragment, None).unwrap();
 
+    let vertex = r#"
+    #version 150
+
+    in vec2 position;
+    in vec2 center;
+    in float radius;
+    in vec3 instance_color;
+
+    uniform mat4 view;
+    uniform mat4 proj;
+
+    out vec3 vertex_color;
+
+    void main() {
+        vertex_color = instance_color;
+        gl_Position = proj * view * vec4(center + radius * position, 0, 1);
+    }
+    "#;
+    let fragment = r#"
+    #version 150
+
+    in vec3 vertex_color;
+
+    out vec4 color;
+
+    void main() {
+        color = vec4(vertex_color, 1);
+    }
+    "#;
+
+    let instanced_program = glium::Program::from_source(&display, vertex, fragment, None).unwrap();
+    let mut instances = None;
+
     let mut last_time = std::time::Instant::now();
 
     let mut cursor = None;
@@ -140,13 +232,12 @@ where
         *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
 
         match &event {
-            glutin::event::Event::WindowEvent { event, .. } => match event {
-                glutin::event::WindowEvent::CloseRequested => {
+            glutin::event::Event::WindowEvent { event, .. } => {
+                if let glutin::event::WindowEvent::CloseRequested = event {
                     *control_flow = glutin::event_loop::ControlFlow::Exit;
                     return;
                 }
-                _ => (),
-            },
+            }
             glutin::event::Event::NewEvents(glutin::event::StartCause::ResumeTimeReached {
                 ..
             }) => (),
@@ -161,17 +252,12 @@ where
 
         let (width, height) = target.get_dimensions();
 
-        let aspect_ratio = width as f32 / height as f32;
-        let proj = if aspect_ratio < 1.0 {
-            Mat4::diag(1.0, aspect_ratio, 1.0, 1.0)
-        } else {
-            Mat4::diag(1.0 / aspect_ratio, 1.0, 1.0, 1.0)
-        };
+        let proj = projection(width, height);
 
         let mut new_key = None;
 
-        match event {
-            glutin::event::Event::WindowEvent { event, .. } => match event {
+        if let glutin::event::Event::WindowEvent { event, .. } = event {
+            match event {
                 glutin::event::WindowEvent::CursorMoved { position, .. } => {
                     let x = position.x / width as f64 * 2.0 - 1.0;
                     let y = 1.0 - position.y / height as f64 * 2.0;
@@ -200,32 +286,32 @@ where
                             ..
                         },
                     ..
-                } => {
-                    match state {
-                        glutin::event::ElementState::Pressed => {
-                            if !pressed_keys.contains(&key) {
-                                new_key = Some(key);
-                            }
-                            pressed_keys.insert(key);
-                        }
-                        glutin::event::ElementState::Released => {
-                            pressed_keys.remove(&key);
+                } => match state {
+                    glutin::event::ElementState::Pressed => {
+                        if !pressed_keys.contains(&key) {
+                            new_key = Some(key);
                         }
+                        pressed_keys.insert(key);
                     }
-                }
+                    glutin::event::ElementState::Released => {
+                        pressed_keys.remove(&key);
+                    }
+                },
                 _ => (),
-            },
-            _ => (),
+            }
         }
 
         draw(
             Painter {
                 view: &mut view,
-                proj: proj,
+                proj,
                 target: &mut target,
                 circle_verticies: &circle_verticies,
                 square_verticies: &square_verticies,
                 program: &program,
+                instanced_program: &instanced_program,
+                instances: &mut instances,
+                display: &display,
             },
             dt,
             cursor,
@@ -0,0 +1,159 @@
+use crate::{Dot, State};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Severe cases need a bed, without one they are more likely to die. The other cases keep the
+/// fatality of the scenario, which the defaults do not exceed even in a bed.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hospital {
+    pub beds: usize,
+    // fraction of the infected that become severe at the onset of the symptoms
+    pub severe_fraction: f64,
+    pub admitted_fatality: f64,
+    pub unattended_fatality: f64,
+}
+
+impl Default for Hospital {
+    fn default() -> Hospital {
+        Hospital {
+            beds: 20,
+            severe_fraction: 0.2,
+            admitted_fatality: 0.5,
+            unattended_fatality: 0.9,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Care {
+    None,
+    // severe case waiting for a bed
+    Waiting,
+    Admitted,
+}
+
+impl Hospital {
+    // `fatality` applies to the cases that were never severe
+    pub fn fatality(&self, a: &Dot, fatality: f64) -> f64 {
+        match a.care {
+            Care::None => fatality,
+            Care::Waiting => self.unattended_fatality,
+            Care::Admitted => self.admitted_fatality,
+        }
+    }
+
+    /// Gives the free beds to the severe cases that wait since the longest time.
+    pub fn admit(&self, dots: &mut [Dot]) {
+        let (occupied, _) = occupancy(dots);
+        let free = self.beds.saturating_sub(occupied);
+
+        let mut waiting: Vec<(f64, usize)> = dots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, a)| match (a.care, a.state) {
+                (Care::Waiting, State::Infected(end)) => Some((end, i)),
+                _ => None,
+            })
+            .collect();
+        waiting.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+
+        for &(_, i) in waiting.iter().take(free) {
+            dots[i].care = Care::Admitted;
+        }
+    }
+}
+
+/// Number of occupied beds and of severe cases waiting for one.
+pub fn occupancy(dots: &[Dot]) -> (usize, usize) {
+    let admitted = dots.iter().filter(|a| a.care == Care::Admitted).count();
+    let waiting = dots.iter().filter(|a| a.care == Care::Waiting).count();
+    (admitted, waiting)
+}
+
+/// Occupancy over time, streamed to a csv file if one is given.
+pub struct Occupancy {
+    csv: Option<BufWriter<File>>,
+}
+
+impl Occupancy {
+    pub fn new() -> Occupancy {
+        Occupancy { csv: None }
+    }
+
+    pub fn with_csv(path: &str) -> io::Result<Occupancy> {
+        let mut csv = BufWriter::new(File::create(path)?);
+        writeln!(csv, "t,admitted,waiting")?;
+        Ok(Occupancy { csv: Some(csv) })
+    }
+
+    pub fn record(&mut self, t: f64, dots: &[Dot]) {
+        if let Some(csv) = &mut self.csv {
+            let (admitted, waiting) = occupancy(dots);
+            let ok = writeln!(csv, "{},{},{}", t, admitted, waiting).and_then(|_| csv.flush());
+            if let Err(e) = ok {
+                eprintln!("occupancy: {}", e);
+                self.csv = None;
+            }
+        }
+    }
+}
+
+#[test]
+fn admission_order() {
+    use crate::vec2::V;
+
+    let hospital = Hospital {
+        beds: 2,
+        ..Hospital::default()
+    };
+    let mut dots: Vec<Dot> = (0..4).map(|_| Dot::new(V::new(0.0, 0.0))).collect();
+    for (i, a) in dots.iter_mut().enumerate() {
+        a.state = State::Infected(10.0 - i as f64);
+        a.care = Care::Waiting;
+    }
+    dots[0].care = Care::Admitted;
+
+    hospital.admit(&mut dots);
+    // one bed left, it goes to the oldest case
+    assert_eq!(dots[3].care, Care::Admitted);
+    assert_eq!(dots[2].care, Care::Waiting);
+    assert_eq!(occupancy(&dots), (2, 2));
+}
+
+#[test]
+fn mild_cases_keep_the_fatality() {
+    use crate::epidemic::epidemic;
+    use crate::scenario::Scenario;
+    use crate::strain::Strains;
+    use crate::vec2::V;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let scenario = Scenario {
+        fatality: 1.0,
+        hospital: Some(Hospital {
+            severe_fraction: 0.0,
+            ..Hospital::default()
+        }),
+        ..Scenario::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut strains = Strains::new(&scenario.strains);
+    let mut dots = vec![Dot::new(V::new(0.0, 0.0))];
+    dots[0].state = State::Infected(1.0);
+    epidemic(&mut dots, &mut strains, &scenario, 1.5, 0.5, &mut rng);
+    assert!(dots[0].state == State::Dead);
+
+    let hospital = scenario.hospital.unwrap();
+    dots[0].care = Care::Waiting;
+    assert_eq!(
+        hospital.fatality(&dots[0], 0.1),
+        hospital.unattended_fatality
+    );
+    // a bed does not make a severe case safer than a mild one
+    let fatality = Scenario::default().fatality;
+    assert!(hospital.admitted_fatality >= fatality);
+    assert!(hospital.unattended_fatality >= hospital.admitted_fatality);
+}
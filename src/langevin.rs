@@ -0,0 +1,154 @@
+use crate::awareness::weight;
+use crate::montecarlo::SweepStats;
+use crate::potential::global_gradient;
+use crate::scenario::Scenario;
+use crate::vec2::V;
+use crate::{Dot, State};
+use rand::Rng;
+use rand_distr::StandardNormal;
+
+// the drift of a single step is capped, the pair potentials diverge when two dots overlap
+const MAX_DRIFT: f64 = 0.1;
+
+fn forces(dots: &[Dot], scenario: &Scenario) -> Vec<V> {
+    let mut f = vec![V::new(0.0, 0.0); dots.len()];
+    for i in 0..dots.len() {
+        let a = &dots[i];
+        f[i] -= global_gradient(a.new_pos, scenario.global_strength);
+
+        for j in i + 1..dots.len() {
+            let b = &dots[j];
+            let x = a.new_pos - b.new_pos;
+            let r = x.norm();
+            if r == 0.0 {
+                continue;
+            }
+            let pot = scenario
+                .interactions
+                .get(a.state.compartment(), b.state.compartment());
+            let fa = (-pot.derivative(r) / r) * x;
+            f[i] += weight(a, b, scenario) * fa;
+            f[j] -= weight(b, a, scenario) * fa;
+        }
+    }
+    f
+}
+
+/// Overdamped langevin dynamics during `duration` seconds, in steps of `dt`:
+/// `dx = F / friction dt + sqrt(2 kT dt / friction) xi`
+pub fn langevin<R: Rng>(
+    dots: &mut [Dot],
+    scenario: &Scenario,
+    t: f64,
+    duration: f64,
+    friction: f64,
+    dt: f64,
+    rng: &mut R,
+) -> SweepStats {
+    let radius = scenario.radius;
+    let start: Vec<V> = dots.iter().map(|a| a.new_pos).collect();
+
+    let steps = f64::max((duration / dt).ceil(), 1.0) as usize;
+    let dt = duration / steps as f64;
+
+    for step in 0..steps {
+        let kt = scenario.temperature(t + step as f64 * dt);
+        let f = forces(dots, scenario);
+
+        for (a, f) in dots.iter_mut().zip(f) {
+            if a.state == State::Dead {
+                continue;
+            }
+
+            let mut drift = (dt / friction) * f;
+            if drift.norm() > MAX_DRIFT {
+                drift *= MAX_DRIFT / drift.norm();
+            }
+            let sigma = (2.0 * kt * a.mobility * dt / friction).sqrt();
+            let noise = V::new(rng.sample(StandardNormal), rng.sample(StandardNormal));
+
+            let mut x = a.new_pos + drift + sigma * noise;
+            // reflecting wall, a step overshooting by more than the diameter stops at the wall
+            let r = x.norm();
+            if r >= radius {
+                let inside = radius * (1.0 - 1e-9);
+                let d = 2.0 * radius - r;
+                let d = if d < 0.0 { inside } else { d.min(inside) };
+                x *= d / r;
+            }
+            a.new_pos = x;
+        }
+    }
+
+    let mut stats = SweepStats::default();
+    let mut steps = 0.0;
+    for (a, x) in dots.iter_mut().zip(start.iter()) {
+        if a.state == State::Dead {
+            continue;
+        }
+        let p = a.new_pos;
+        a.new_pos = *x;
+        a.mov(p, t, duration);
+
+        stats.proposed += 1;
+        stats.accepted += 1;
+        steps += (p - *x).norm();
+    }
+    if stats.proposed > 0 {
+        stats.mean_step = steps / stats.proposed as f64;
+    }
+    stats
+}
+
+#[test]
+fn free_diffusion() {
+    use crate::potential::{Interactions, NoInteraction};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    // without forces the mean squared displacement is 4 D t with D = kT / friction
+    let scenario = Scenario {
+        radius: 1000.0,
+        interactions: Interactions::uniform(Arc::new(NoInteraction)),
+        global_strength: 0.0,
+        temperature: 0.5,
+        ..Scenario::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut dots: Vec<Dot> = (0..1000).map(|_| Dot::new(V::new(0.0, 0.0))).collect();
+
+    langevin(&mut dots, &scenario, 0.0, 2.0, 4.0, 0.1, &mut rng);
+
+    let msd = dots
+        .iter()
+        .map(|a| V::dot(a.new_pos, a.new_pos))
+        .sum::<f64>()
+        / dots.len() as f64;
+    let expected = 4.0 * 0.5 / 4.0 * 2.0;
+    assert!((msd / expected - 1.0).abs() < 0.1, "{}", msd);
+}
+
+#[test]
+fn large_steps() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // the noise of a single step is far larger than the disk
+    let scenario = Scenario {
+        radius: 1.0,
+        temperature: 1e6,
+        ..Scenario::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut dots: Vec<Dot> = (0..100)
+        .map(|i| Dot::new(V::new(0.5, 0.001 * i as f64)))
+        .collect();
+
+    langevin(&mut dots, &scenario, 0.0, 1.0, 1.0, 1.0, &mut rng);
+
+    for a in dots.iter() {
+        let r = a.new_pos.norm();
+        assert!(r < 1.0 && r > 0.9, "{}", r);
+    }
+}
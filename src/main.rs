@@ -2,244 +2,723 @@
 extern crate glium;
 extern crate rand;
 extern crate rand_distr;
+mod aerosol;
+mod awareness;
+mod calibration;
+mod diagnostics;
+mod ensemble;
+mod epidemic;
+mod field;
+mod fomite;
 mod gl;
+mod hospital;
+mod langevin;
+mod montecarlo;
+mod population;
+mod potential;
+mod proposal;
+mod record;
+mod scenario;
+mod snapshot;
+mod strain;
+mod sweep;
+mod trajectory;
+mod treatment;
 mod vec2;
-
+mod world;
+
+use calibration::Abc;
+use diagnostics::Diagnostics;
+use ensemble::Ensemble;
+use epidemic::Route;
+use field::Grid;
+use gl::canvas::Canvas;
 use gl::math::Mat4;
+use gl::raster::Raster;
+use gl::svg::Svg;
 use gl::window::animation;
 use glium::glutin::event::VirtualKeyCode;
-use rand::{thread_rng, Rng};
-use rand_distr::{Bernoulli, Cauchy, Uniform};
-use std::time::{Duration, Instant};
+use hospital::{Care, Occupancy};
+use population::Seeding;
+use rand::{thread_rng, SeedableRng};
+use record::{Action, Recorder, Replay};
+use scenario::Scenario;
+use serde::{Deserialize, Serialize};
+use snapshot::SimRng;
+use strain::Strains;
+use sweep::{Design, Range};
+use trajectory::{export, Trajectory};
+use treatment::{heal, Priority};
 use vec2::V;
+use world::World;
+
+// seconds of simulation between two Monte-Carlo steps of the window and the renderer
+const STEP: f64 = 0.3;
+
+// error of the files with a bad content
+fn invalid<E: ToString>(e: E) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())
+}
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+// the times are the ends of the stages, in seconds of simulation
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
 enum State {
     Susceptible,
-    Asymptomatic(Instant),
-    Infected(Instant),
-    Healed(Instant),
+    Asymptomatic(f64),
+    Infected(f64),
+    Healed(f64),
+    // infinite when the immunity never wanes
+    Recovered(#[serde(with = "snapshot::time")] f64),
+    Dead,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compartment {
+    Susceptible,
+    Asymptomatic,
+    Infected,
+    Healed,
     Recovered,
     Dead,
 }
 
+impl Compartment {
+    pub const COUNT: usize = 6;
+    pub const ALL: [Compartment; Compartment::COUNT] = [
+        Compartment::Susceptible,
+        Compartment::Asymptomatic,
+        Compartment::Infected,
+        Compartment::Healed,
+        Compartment::Recovered,
+        Compartment::Dead,
+    ];
+}
+
 impl State {
+    fn compartment(&self) -> Compartment {
+        match self {
+            State::Susceptible => Compartment::Susceptible,
+            State::Asymptomatic(_) => Compartment::Asymptomatic,
+            State::Infected(_) => Compartment::Infected,
+            State::Healed(_) => Compartment::Healed,
+            State::Recovered(_) => Compartment::Recovered,
+            State::Dead => Compartment::Dead,
+        }
+    }
     fn color(&self) -> [f32; 3] {
         match self {
             State::Susceptible => [1.0, 1.0, 1.0],
             State::Asymptomatic(_) => [1.0, 1.0, 1.0],
             State::Infected(_) => [1.0, 0.0, 0.0],
             State::Healed(_) => [0.0, 0.0, 1.0],
-            State::Recovered => [0.0, 1.0, 0.0],
+            State::Recovered(_) => [0.0, 1.0, 0.0],
             State::Dead => [1.0, 0.0, 1.0],
         }
     }
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 struct Dot {
     new_pos: V,
-    new_t: Instant,
+    new_t: f64,
 
     last_pos: V,
-    last_t: Instant,
+    last_t: f64,
 
     state: State,
+    // exposure accumulated while susceptible
+    dose: f64,
+    inhaled: f64,
+    route: Option<Route>,
+
+    // current strain while infected, and the strains recovered from
+    strain: usize,
+    immunity: Vec<usize>,
+
+    care: Care,
+    age: f64,
+
+    // multiplies the temperature of the metropolis acceptance
+    mobility: f64,
+
+    // scales the avoidance of the dots with symptoms, see `awareness`
+    awareness: f64,
+    fatigue: f64,
 }
 
 impl Dot {
     fn new(pos: V) -> Dot {
         Dot {
             new_pos: pos,
-            new_t: Instant::now() + Duration::from_secs_f64(1.0),
+            new_t: 0.0,
 
             last_pos: pos,
-            last_t: Instant::now(),
+            last_t: 0.0,
 
             state: State::Susceptible,
+            dose: 0.0,
+            inhaled: 0.0,
+            route: None,
+
+            strain: 0,
+            immunity: Vec::new(),
+
+            care: Care::None,
+            age: 0.0,
+
+            mobility: 1.0,
+
+            awareness: 0.0,
+            fatigue: 0.0,
         }
     }
 
-    fn pos(&self) -> V {
-        let x = self.last_t.elapsed().as_secs_f64() / (self.new_t - self.last_t).as_secs_f64();
-        let x = f64::max(f64::min(x, 1.0), 0.0);
+    // infected dots take the color of their strain
+    fn color(&self, strains: &Strains) -> [f32; 3] {
+        match self.state {
+            State::Infected(_) => strains.list[self.strain].color,
+            _ => self.state.color(),
+        }
+    }
+
+    fn pos(&self, t: f64) -> V {
+        if self.new_t <= self.last_t {
+            return self.new_pos;
+        }
+        let x = (t - self.last_t) / (self.new_t - self.last_t);
+        let x = x.clamp(0.0, 1.0);
         x * self.new_pos + (1.0 - x) * self.last_pos
     }
-    fn mov(&mut self, new_pos: V, dt: f64) {
-        self.last_pos = self.pos();
-        self.last_t = Instant::now();
+    fn mov(&mut self, new_pos: V, t: f64, dt: f64) {
+        self.last_pos = self.pos(t);
+        self.last_t = t;
 
         self.new_pos = new_pos;
-        self.new_t = Instant::now() + Duration::from_secs_f64(dt);
+        self.new_t = t + dt;
+    }
+}
+
+// sum of the layers, shaded relative to the most concentrated cell
+fn draw_layers<C: Canvas>(canvas: &mut C, layers: &[Grid], color: [f32; 3]) {
+    let grid = match layers.first() {
+        Some(grid) => grid,
+        None => return,
+    };
+    let total: Vec<f64> = (0..grid.values.len())
+        .map(|k| layers.iter().map(|g| g.values[k]).sum())
+        .collect();
+    let max = total.iter().cloned().fold(0.0, f64::max);
+    if max <= 0.0 {
+        return;
     }
+    let h = grid.cell as f32;
+    for (k, &v) in total.iter().enumerate() {
+        let c = (v / max) as f32;
+        if grid.open[k] && c > 0.01 {
+            let x = grid.corner(k);
+            canvas.draw_rect(
+                x.0 as f32,
+                x.1 as f32,
+                h,
+                h,
+                [c * color[0], c * color[1], c * color[2]],
+            );
+        }
+    }
+}
+
+fn draw_dots<C: Canvas>(canvas: &mut C, dots: &[Dot], strains: &Strains, t: f64) {
+    let r = 0.02;
+    let circles: Vec<_> = dots
+        .iter()
+        .map(|a| {
+            let x = a.pos(t);
+            (x.0 as f32, x.1 as f32, r, a.color(strains))
+        })
+        .collect();
+    canvas.draw_circles(&circles);
+}
+
+// the frame at time `t`, the same on every canvas
+fn draw_world<C: Canvas>(canvas: &mut C, scenario: &Scenario, world: &World, t: f64) {
+    canvas.set_view(Mat4::scale(1.0 / scenario.radius as f32));
+    draw_layers(canvas, &world.air, [0.0, 0.4, 0.5]);
+    draw_layers(canvas, &world.surfaces, [0.4, 0.25, 0.0]);
+    draw_dots(canvas, &world.dots, &world.strains, t);
+}
+
+// names and colors of the dots, the asymptomatic ones look susceptible and the infected ones
+// take the color of their strain
+fn legend(strains: &Strains) -> Vec<(String, [f32; 3])> {
+    let mut x = vec![(
+        "Susceptible or Asymptomatic".to_string(),
+        State::Susceptible.color(),
+    )];
+    for (k, s) in strains.list.iter().enumerate() {
+        let label = match (strains.len(), s.parent) {
+            (1, _) => "Infected".to_string(),
+            (_, None) => format!("Infected, strain {}", k),
+            (_, Some(p)) => format!("Infected, strain {} from {}", k, p),
+        };
+        x.push((label, s.color));
+    }
+    for state in [State::Healed(0.0), State::Recovered(0.0), State::Dead].iter() {
+        x.push((format!("{:?}", state.compartment()), state.color()));
+    }
+    x
 }
 
-fn montecarlo(dots: &mut Vec<Dot>) {
-    let mut rng = thread_rng();
-    for i in 0..dots.len() {
-        let a = dots[i].clone();
+// writes the frame at time `t` as an svg file, with a legend
+fn write_svg(path: &str, scenario: &Scenario, world: &World, t: f64) -> std::io::Result<()> {
+    let mut svg = Svg::new(800, 800);
+    draw_world(&mut svg, scenario, world, t);
+    svg.legend(&legend(&world.strains));
+    svg.save(path)
+}
 
-        if a.state == State::Dead {
-            continue;
+// a failed write stops the recording instead of the simulation
+fn record_tick(recorder: &mut Option<Recorder>, t: f64, world: &World) {
+    if let Some(x) = recorder {
+        if let Err(e) = x.record(t, world) {
+            eprintln!("record: {}", e);
+            *recorder = None;
         }
+    }
+}
 
-        let mut dx;
-        loop {
-            let phi = rng.sample(Uniform::new(0.0, 2.0 * std::f64::consts::PI));
-            dx = rng.sample(Cauchy::new(0.0, 0.10).unwrap()) * V::new(phi.cos(), phi.sin());
-            let new_pos = a.new_pos + dx;
+// where and how the headless renderer writes the frames, the recording, the trajectory and the
+// statistics
+struct Output {
+    size: (u32, u32),
+    fps: f64,
+    frames: Option<String>,
+    video: Option<String>,
+    record: Option<String>,
+    trajectory: Option<Trajectory>,
+    diagnostics: Diagnostics,
+    occupancy: Occupancy,
+}
 
-            if new_pos.norm() < 5.0 {
-                break;
+// steps the world from `t` to `end` and draws `fps` frames per second of simulation into png
+// files and/or an ffmpeg process encoding a video
+fn render(
+    scenario: &Scenario,
+    mut world: World,
+    mut rng: SimRng,
+    mut t: f64,
+    mut t_montecarlo: f64,
+    end: f64,
+    output: Output,
+) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let Output {
+        size: (width, height),
+        fps,
+        frames,
+        video,
+        record,
+        mut trajectory,
+        mut diagnostics,
+        mut occupancy,
+    } = output;
+    let mut raster = Raster::new(width, height);
+    if let Some(dir) = &frames {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut recorder = match record {
+        Some(path) => Some(Recorder::create(&path, scenario.radius, &world)?),
+        None => None,
+    };
+    let mut encoder = match &video {
+        Some(path) => Some(
+            Command::new("ffmpeg")
+                .args([
+                    "-loglevel",
+                    "error",
+                    "-y",
+                    "-f",
+                    "rawvideo",
+                    "-pix_fmt",
+                    "rgb24",
+                ])
+                .args([
+                    "-s",
+                    &format!("{}x{}", width, height),
+                    "-r",
+                    &fps.to_string(),
+                    "-i",
+                    "-",
+                ])
+                .args(["-pix_fmt", "yuv420p", path])
+                .stdin(Stdio::piped())
+                .spawn()?,
+        ),
+        None => None,
+    };
+
+    let mut frame = 0;
+    while t < end {
+        if t >= t_montecarlo {
+            t_montecarlo = t + STEP;
+            let stats = world.step(scenario, t, STEP, &mut rng);
+            diagnostics.record(t, stats, &world.dots, scenario);
+            record_tick(&mut recorder, t, &world);
+            export(&mut trajectory, t, &world.dots);
+            if scenario.hospital.is_some() {
+                occupancy.record(t, &world.dots);
             }
         }
 
-        fn pairwise_attractive(r: f64) -> f64 {
-            let d = 0.04;
-            3.0 * ((d / r).powi(12) - (d / r).powi(6))
+        raster.clear();
+        draw_world(&mut raster, scenario, &world, t);
+
+        if let Some(dir) = &frames {
+            raster.save_png(&format!("{}/frame_{:05}.png", dir, frame))?;
         }
-        fn pairwise_repulsive(r: f64) -> f64 {
-            let d = 0.04;
-            3.0 * (d / r).powi(2)
+        if let Some(child) = &mut encoder {
+            let stdin = child.stdin.as_mut();
+            let stdin = stdin.ok_or_else(|| std::io::Error::other("no input to ffmpeg"))?;
+            stdin.write_all(raster.rgb())?;
         }
+        frame += 1;
+        t += 1.0 / fps;
+    }
 
-        let mut d_energy = 0.0;
-        for b in dots.iter() {
-            let r1 = (a.new_pos - b.new_pos).norm();
-            let r2 = (a.new_pos + dx - b.new_pos).norm();
+    if let Some(mut child) = encoder {
+        drop(child.stdin.take());
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(std::io::Error::other(format!("ffmpeg failed: {}", status)));
+        }
+    }
+    Ok(())
+}
 
-            if r1 == 0.0 || r2 == 0.0 {
-                continue;
+fn main() {
+    let mut diagnostics = Diagnostics::new();
+    let mut occupancy = None;
+    let mut ensemble = Ensemble::default();
+    // set by --ensemble, the other options only configure the runs
+    let mut batch = false;
+    let mut bands = None;
+    let mut runs = None;
+    let mut design: Option<Design> = None;
+    let mut ranges = Vec::new();
+    let mut results = None;
+    let mut indices = None;
+    let mut cases = None;
+    let mut abc = Abc::default();
+    let mut posterior = None;
+    let mut save = "snapshot.bin".to_string();
+    let mut svg = "frame.svg".to_string();
+    let mut load = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut trajectory = None;
+    let mut frames = None;
+    let mut video = None;
+    let mut size = (800, 800);
+    let mut fps = 30.0;
+    let mut agents = None;
+    let mut seeding = None;
+    let mut scenario = Scenario::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scenario" => {
+                let path = args.next().expect("--scenario <file.json>");
+                scenario = Scenario::read(&path).unwrap();
+            }
+            "--diagnostics" => {
+                let path = args.next().expect("--diagnostics <file.csv>");
+                diagnostics = Diagnostics::with_csv(&path).unwrap();
+            }
+            "--occupancy" => {
+                occupancy = Some(args.next().expect("--occupancy <file.csv>"));
+            }
+            "--ensemble" => {
+                let n = args.next().and_then(|x| x.parse().ok());
+                let n = n.expect("--ensemble <number of runs>");
+                ensemble.runs = n;
+                batch = true;
+            }
+            "--duration" => {
+                let x = args.next().and_then(|x| x.parse().ok());
+                let x = x.expect("--duration <seconds>");
+                ensemble.duration = x;
+            }
+            "--seed" => {
+                let x = args.next().and_then(|x| x.parse().ok());
+                let x = x.expect("--seed <integer>");
+                ensemble.seed = x;
+            }
+            "--bands" => bands = Some(args.next().expect("--bands <file.csv>")),
+            "--runs" => runs = Some(args.next().expect("--runs <file.csv>")),
+            "--sweep" => {
+                let x = args.next().and_then(|x| Design::parse(&x));
+                design = Some(x.expect("--sweep grid:<levels>|lhs:<n>|morris:<n>|sobol:<n>"));
+            }
+            "--vary" => {
+                let x = args.next().and_then(|x| Range::parse(&x));
+                ranges.push(x.expect("--vary <parameter>:<low>:<high>"));
+            }
+            "--results" => results = Some(args.next().expect("--results <file.csv>")),
+            "--indices" => indices = Some(args.next().expect("--indices <file.csv>")),
+            "--calibrate" => cases = Some(args.next().expect("--calibrate <cases.csv>")),
+            "--samples" => {
+                let x = args.next().and_then(|x| x.parse().ok());
+                abc.samples = x.expect("--samples <number of draws>");
+            }
+            "--day" => {
+                let x = args.next().and_then(|x| x.parse().ok());
+                abc.day = x.expect("--day <seconds per day of the observed cases>");
+            }
+            "--accept" => {
+                let x = args.next().and_then(|x| x.parse().ok());
+                abc.accept = x.expect("--accept <fraction>");
+            }
+            "--save" => save = args.next().expect("--save <file.json|file.bin>"),
+            "--svg" => svg = args.next().expect("--svg <file.svg>"),
+            "--record" => record = Some(args.next().expect("--record <file>")),
+            "--replay" => replay = Some(args.next().expect("--replay <file>")),
+            "--trajectory" => {
+                let path = args
+                    .next()
+                    .expect("--trajectory <file.xyz|file.extxyz|file.arrow>");
+                trajectory = Some(Trajectory::create(&path).unwrap());
+            }
+            "--population" => {
+                let path = args.next().expect("--population <file.csv|file.json>");
+                agents = Some(population::read(&path).unwrap());
+            }
+            "--seeding" => {
+                let x = args.next().and_then(|x| Seeding::parse(&x));
+                seeding = Some(x.expect("--seeding none|random:<n>|cluster:<x>:<y>:<n>|edge:<n>"));
+            }
+            "--frames" => frames = Some(args.next().expect("--frames <directory>")),
+            "--video" => video = Some(args.next().expect("--video <file.mp4>")),
+            "--size" => {
+                let x = args.next().and_then(|x| {
+                    let (w, h) = x.split_once('x')?;
+                    Some((w.parse().ok()?, h.parse().ok()?))
+                });
+                let x = x.filter(|&(w, h)| w > 0 && h > 0);
+                size = x.expect("--size <width>x<height>");
+            }
+            "--fps" => {
+                let x = args.next().and_then(|x| x.parse().ok());
+                let x = x.filter(|&x: &f64| x > 0.0 && x.is_finite());
+                fps = x.expect("--fps <frames per second>");
+            }
+            "--load" => load = Some(args.next().expect("--load <snapshot>")),
+            "--posterior" => posterior = Some(args.next().expect("--posterior <file.csv>")),
+            _ => {
+                eprintln!("unknown argument {}", arg);
+                std::process::exit(1);
             }
-
-            d_energy += match (a.state, b.state) {
-                (State::Infected(_), State::Infected(_)) => {
-                    pairwise_attractive(r1) - pairwise_attractive(r2)
-                }
-                (State::Infected(_), _) | (State::Healed(_), _) => {
-                    pairwise_repulsive(r1) - pairwise_repulsive(r2)
-                }
-                (_, State::Infected(_)) | (_, State::Healed(_)) => {
-                    pairwise_repulsive(r1) - pairwise_repulsive(r2)
-                }
-                (_, _) => pairwise_attractive(r1) - pairwise_attractive(r2),
-            };
         }
+    }
 
-        fn global_potential(mut x: V) -> f64 {
-            x *= std::f64::consts::PI;
-            3.0 * (x.0.cos() + x.1.cos())
-        }
+    // the states given with the population are kept unless a seeding is asked for
+    match (seeding, &agents) {
+        (Some(x), _) => scenario.seeding = x,
+        (None, Some(_)) => scenario.seeding = Seeding::None,
+        (None, None) => (),
+    }
+    // the batch runs build their dots from the agents too, so they are checked once here
+    if let Some(x) = &agents {
+        population::dots(x, &scenario, &mut thread_rng()).unwrap();
+        ensemble.population = Some(x.clone());
+    }
+    // the varied parameters stay in their domain, rather than failing inside a run
+    for x in ranges.iter() {
+        x.check(&scenario).unwrap();
+    }
 
-        // global potential
-        d_energy += global_potential(a.new_pos) - global_potential(a.new_pos + dx);
-        let p = if d_energy > 0.0 { 1.0 } else { d_energy.exp() };
+    // headless fit of the varied parameters to the observed daily cases
+    if let Some(path) = cases {
+        let observed = calibration::read_cases(&path).unwrap();
+        let config = ensemble;
+        let mut rng = thread_rng();
+        let x = calibration::abc(&scenario, &ranges, &observed, &abc, &config, &mut rng);
+        print!("{}", calibration::summary(&ranges, &x));
+        if let Some(path) = posterior {
+            calibration::write_posterior(&path, &ranges, &x).unwrap();
+        }
+        return;
+    }
 
-        if rng.sample(Bernoulli::new(p).unwrap()) {
-            let p = dots[i].new_pos + dx;
-            dots[i].mov(p, 0.2);
+    // headless sweep over the parameters, with an ensemble at every point
+    if let Some(design) = design {
+        let config = ensemble;
+        let mut rng = thread_rng();
+        let (unit, points) = sweep::sweep(&scenario, &ranges, &design, &config, &mut rng);
+        println!("{} points of {} runs", points.len(), config.runs);
+        if let Some(path) = results {
+            sweep::write_results(&path, &ranges, &points).unwrap();
         }
+        if let Some(path) = indices {
+            sweep::write_indices(&path, &design, &ranges, &unit, &points).unwrap();
+        }
+        return;
     }
-}
 
-fn index_twice<T>(slc: &mut [T], a: usize, b: usize) -> Option<(&mut T, &mut T)> {
-    if a == b || a >= slc.len() || b >= slc.len() {
-        None
-    } else {
-        // safe because a, b are in bounds and distinct
-        unsafe {
-            let ar = &mut *(slc.get_unchecked_mut(a) as *mut _);
-            let br = &mut *(slc.get_unchecked_mut(b) as *mut _);
-            Some((ar, br))
+    // headless batch of runs instead of the window
+    if batch {
+        let x = ensemble::ensemble(&scenario, &ensemble, trajectory);
+        print!("{}", ensemble::summary(&x));
+        if let Some(path) = bands {
+            ensemble::write_bands(&path, &x, &[0.05, 0.25, 0.5, 0.75, 0.95]).unwrap();
+        }
+        if let Some(path) = runs {
+            ensemble::write_runs(&path, &x).unwrap();
+        }
+        if let Some(path) = occupancy {
+            ensemble::write_occupancy(&path, &x).unwrap();
         }
+        return;
     }
-}
 
-fn main() {
-    let mut dots = Vec::new();
+    if let Some(path) = replay {
+        play(Replay::load(&path).unwrap());
+    }
 
-    let mut rng = thread_rng();
-    for _ in 0..1400 {
-        let phi = rng.sample(Uniform::new(0.0, 2.0 * std::f64::consts::PI));
-        let x = rng.sample(Uniform::new(0.0, 5.0)) * V::new(phi.cos(), phi.sin());
-        dots.push(Dot::new(x));
+    let (scenario, mut world, mut rng, mut t, mut t_montecarlo) = match load {
+        Some(path) => {
+            let x = snapshot::load(&path).unwrap();
+            (x.scenario, x.world, x.rng, x.t, x.next_step)
+        }
+        None => {
+            let mut rng = SimRng::from_entropy();
+            let world = match &agents {
+                Some(x) => {
+                    let dots = population::dots(x, &scenario, &mut rng).unwrap();
+                    World::with_dots(&scenario, dots, &mut rng)
+                }
+                None => World::new(&scenario, 1400, &mut rng),
+            };
+            (scenario, world, rng, 0.0, 0.0)
+        }
+    };
+
+    let mut occupancy = match occupancy {
+        Some(path) => Occupancy::with_csv(&path).unwrap(),
+        None => Occupancy::new(),
+    };
+
+    // headless rendering of `duration` seconds from the start or the snapshot
+    if frames.is_some() || video.is_some() {
+        let end = t + ensemble.duration;
+        let output = Output {
+            size,
+            fps,
+            frames,
+            video,
+            record,
+            trajectory,
+            diagnostics,
+            occupancy,
+        };
+        render(&scenario, world, rng, t, t_montecarlo, end, output).unwrap();
+        return;
     }
-    dots[0].state = State::Asymptomatic(Instant::now() + Duration::from_secs_f64(5.0));
 
-    let mut t = 0.0;
-    let mut t_montecarlo = 0.0;
+    let mut recorder = record.map(|path| Recorder::create(&path, scenario.radius, &world).unwrap());
 
     animation(move |mut painter, dt, _cursor, _left, _right, key| {
         t += dt;
 
-        *painter.view = Mat4::scale(1.0 / 5.0);
+        draw_world(&mut painter, &scenario, &world, t);
 
-        let r = 0.02;
+        // manual treatment on top of the automatic one
+        if key == Some(VirtualKeyCode::H) {
+            let priority = scenario.treatment.map_or(Priority::Order, |x| x.priority);
+            for i in treatment::next(&world.dots, priority, 1, &mut rng) {
+                heal(&mut world.dots[i], &scenario, t);
+                println!("heal someone");
+                if let Some(recorder) = &mut recorder {
+                    recorder.action(Action::Heal { dot: i });
+                }
+            }
+            record_tick(&mut recorder, t, &world);
+        }
 
-        for a in dots.iter_mut() {
-            let x = a.pos();
-            painter.draw_circle(x.0 as f32, x.1 as f32, r as f32, a.state.color());
+        if key == Some(VirtualKeyCode::I) {
+            for &route in [Route::Contact, Route::Aerosol, Route::Fomite].iter() {
+                let n = world.dots.iter().filter(|a| a.route == Some(route)).count();
+                println!("infected by {:?}: {}", route, n);
+            }
         }
 
-        if key == Some(VirtualKeyCode::H) {
-            for a in &mut dots {
-                if let State::Infected(_) = a.state {
-                    a.state = State::Healed(Instant::now() + Duration::from_secs_f64(5.0));
-                    println!("heal someone");
-                    break;
-                }
+        if key == Some(VirtualKeyCode::S) {
+            match snapshot::save(&save, t, t_montecarlo, &scenario, &world, &rng) {
+                Ok(()) => println!("saved {}", save),
+                Err(e) => eprintln!("snapshot: {}", e),
             }
         }
 
-        if t > t_montecarlo {
-            let dmt = 0.3;
-            t_montecarlo = t + dmt;
-
-            for i in 0..dots.len() {
-                for j in i + 1..dots.len() {
-                    let (a, b) = index_twice(&mut dots, i, j).unwrap();
-                    if (a.pos() - b.pos()).norm() < 3.0 * r {
-                        match (a.state, b.state) {
-                            (State::Infected(_), State::Susceptible)
-                            | (State::Asymptomatic(_), State::Susceptible) => {
-                                b.state = State::Asymptomatic(
-                                    Instant::now() + Duration::from_secs_f64(5.0),
-                                );
-                            }
-                            (State::Susceptible, State::Infected(_))
-                            | (State::Susceptible, State::Asymptomatic(_)) => {
-                                a.state = State::Asymptomatic(
-                                    Instant::now() + Duration::from_secs_f64(5.0),
-                                );
-                            }
-                            _ => (),
-                        }
-                    }
-                }
+        if key == Some(VirtualKeyCode::V) {
+            match write_svg(&svg, &scenario, &world, t) {
+                Ok(()) => println!("saved {}", svg),
+                Err(e) => eprintln!("svg: {}", e),
+            }
+        }
 
-                if let State::Asymptomatic(t) = dots[i].state {
-                    if t < Instant::now() {
-                        dots[i].state =
-                            State::Infected(Instant::now() + Duration::from_secs_f64(10.0));
-                    }
-                }
-                if let State::Infected(t) = dots[i].state {
-                    if t < Instant::now() {
-                        if rng.sample(Bernoulli::new(0.5).unwrap()) {
-                            dots[i].state = State::Dead;
-                        } else {
-                            dots[i].state = State::Recovered;
-                        }
-                    }
-                }
-                if let State::Healed(t) = dots[i].state {
-                    if t < Instant::now() {
-                        dots[i].state = State::Recovered;
-                    }
-                }
+        if t > t_montecarlo {
+            t_montecarlo = t + STEP;
+
+            let stats = world.step(&scenario, t, STEP, &mut rng);
+            diagnostics.record(t, stats, &world.dots, &scenario);
+            record_tick(&mut recorder, t, &world);
+            export(&mut trajectory, t, &world.dots);
+            if scenario.hospital.is_some() {
+                occupancy.record(t, &world.dots);
             }
+        }
+    });
+}
 
-            montecarlo(&mut dots);
+// replay in the window: space pauses, up and down change the speed, R reverses, left and right
+// jump by 5 seconds and the left button scrubs along the bar at the bottom
+fn play(mut replay: Replay) -> ! {
+    let radius = replay.radius;
+    let (start, end) = (replay.start(), replay.end());
+    let mut t = start;
+    let mut speed = 1.0;
+    let mut paused = false;
+
+    animation(move |mut painter, dt, cursor, left, _right, key| {
+        match key {
+            Some(VirtualKeyCode::Space) => paused = !paused,
+            Some(VirtualKeyCode::Up) => speed *= 2.0,
+            Some(VirtualKeyCode::Down) => speed /= 2.0,
+            Some(VirtualKeyCode::R) => speed = -speed,
+            Some(VirtualKeyCode::Left) => t -= 5.0,
+            Some(VirtualKeyCode::Right) => t += 5.0,
+            _ => (),
+        }
+        if !paused {
+            t += speed * dt;
         }
+        if let (true, Some((x, _))) = (left, cursor) {
+            t = start + (x / radius + 1.0) / 2.0 * (end - start);
+        }
+        t = t.clamp(start, end + 1.0);
+
+        for (s, action) in replay.seek(t) {
+            println!("{:.1}: {:?}", s, action);
+        }
+
+        painter.set_view(Mat4::scale(1.0 / radius as f32));
+        draw_dots(&mut painter, &replay.dots, &replay.strains, t);
+
+        let w = 2.0 * radius as f32;
+        let progress = ((t - start) / (end - start).max(1e-9)).min(1.0) as f32;
+        let y = -1.08 * radius as f32;
+        painter.draw_rect(-w / 2.0, y, w, 0.02 * w, [0.2, 0.2, 0.2]);
+        painter.draw_rect(-w / 2.0, y, progress * w, 0.02 * w, [0.7, 0.7, 0.7]);
     });
 }
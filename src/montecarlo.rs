@@ -0,0 +1,359 @@
+use crate::awareness::weight;
+use crate::potential::global_potential;
+use crate::proposal::Proposal;
+use crate::scenario::Scenario;
+use crate::vec2::V;
+use crate::{Dot, State};
+use rand::Rng;
+use rand_distr::Bernoulli;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// How proposals leaving the disk are treated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Boundary {
+    // moves ending outside are rejected
+    Reject,
+    // moves are mirrored back through the wall, too long moves are rejected
+    Reflect,
+    // proposals are redrawn until inside and the acceptance corrects for the normalization
+    Truncated,
+}
+
+// draws of a truncated proposal before giving up on the move
+const MAX_DRAWS: usize = 1000;
+
+// fraction of the circle of radius `l` centered at distance `rho` from the origin inside the disk
+fn fraction_inside(rho: f64, l: f64, radius: f64) -> f64 {
+    if rho == 0.0 {
+        return if l < radius { 1.0 } else { 0.0 };
+    }
+    let c = (radius * radius - rho * rho - l * l) / (2.0 * rho * l);
+    1.0 - c.clamp(-1.0, 1.0).acos() / PI
+}
+
+// probability for a proposal starting at distance `rho` from the origin to end inside the disk
+fn inside_probability(proposal: &Proposal, rho: f64, radius: f64) -> f64 {
+    // only the lengths above `radius - rho` can leave, integrated with l = exp(s)
+    let n = 2000;
+    let s0 = f64::max(radius - rho, 1e-9 * radius).ln();
+    let s1 = (1e6 * radius).ln();
+    let ds = (s1 - s0) / n as f64;
+    let mut outside = 0.0;
+    for i in 0..n {
+        let l = (s0 + (i as f64 + 0.5) * ds).exp();
+        let density = proposal.density(V::new(l, 0.0)) * 2.0 * PI * l;
+        outside += density * (1.0 - fraction_inside(rho, l, radius)) * l * ds;
+    }
+    1.0 - outside
+}
+
+// `inside_probability` tabulated on `radius` and linearly interpolated
+struct Normalization {
+    radius: f64,
+    table: Vec<f64>,
+}
+
+impl Normalization {
+    fn new(proposal: &Proposal, radius: f64) -> Normalization {
+        let n = 128;
+        let table = (0..=n)
+            .map(|i| inside_probability(proposal, radius * i as f64 / n as f64, radius))
+            .collect();
+        Normalization { radius, table }
+    }
+
+    fn get(&self, x: V) -> f64 {
+        let n = self.table.len() - 1;
+        let s = (x.norm() / self.radius).clamp(0.0, 1.0) * n as f64;
+        let i = usize::min(s as usize, n - 1);
+        let w = s - i as f64;
+        (1.0 - w) * self.table[i] + w * self.table[i + 1]
+    }
+}
+
+fn reflect(y: V, radius: f64) -> Option<V> {
+    let r = y.norm();
+    if r < radius {
+        Some(y)
+    } else if r < 2.0 * radius {
+        Some(((2.0 * radius - r) / r) * y)
+    } else {
+        None
+    }
+}
+
+// density of proposing `z` from `x` once the moves outside are reflected
+fn reflected_density(proposal: &Proposal, x: V, z: V, radius: f64) -> f64 {
+    let mut q = proposal.density(z - x);
+    let r = z.norm();
+    if r > 0.0 {
+        let z_out = ((2.0 * radius - r) / r) * z;
+        q += proposal.density(z_out - x) * (2.0 * radius - r) / r;
+    }
+    q
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SweepStats {
+    pub proposed: usize,
+    pub accepted: usize,
+    // mean length of the accepted moves
+    pub mean_step: f64,
+}
+
+impl SweepStats {
+    pub fn acceptance(&self) -> f64 {
+        if self.proposed == 0 {
+            0.0
+        } else {
+            self.accepted as f64 / self.proposed as f64
+        }
+    }
+}
+
+/// Pair energy of every pair of dots plus the global potential of every dot. Each dot of a pair
+/// feels half of the pair energy, multiplied by its awareness weight.
+pub fn energy(dots: &[Dot], scenario: &Scenario) -> f64 {
+    let mut e = 0.0;
+    for i in 0..dots.len() {
+        let a = &dots[i];
+        for b in dots[i + 1..].iter() {
+            let r = (a.new_pos - b.new_pos).norm();
+            if r == 0.0 {
+                continue;
+            }
+            let pot = scenario
+                .interactions
+                .get(a.state.compartment(), b.state.compartment());
+            let w = (weight(a, b, scenario) + weight(b, a, scenario)) / 2.0;
+            e += w * pot.energy(r);
+        }
+        e += global_potential(a.new_pos, scenario.global_strength);
+    }
+    e
+}
+
+/// Metropolis sweeps over the dots, with the tables that depend only on the scenario.
+pub struct Metropolis {
+    normalization: Option<Normalization>,
+}
+
+impl Metropolis {
+    pub fn new(scenario: &Scenario) -> Metropolis {
+        let normalization = if scenario.boundary == Boundary::Truncated {
+            Some(Normalization::new(&scenario.proposal, scenario.radius))
+        } else {
+            None
+        };
+        Metropolis { normalization }
+    }
+
+    pub fn sweep<R: Rng>(
+        &mut self,
+        dots: &mut [Dot],
+        scenario: &Scenario,
+        t: f64,
+        rng: &mut R,
+    ) -> SweepStats {
+        montecarlo(dots, scenario, self.normalization.as_ref(), t, rng)
+    }
+}
+
+fn montecarlo<R: Rng>(
+    dots: &mut [Dot],
+    scenario: &Scenario,
+    normalization: Option<&Normalization>,
+    t: f64,
+    rng: &mut R,
+) -> SweepStats {
+    let temperature = scenario.temperature(t);
+    let radius = scenario.radius;
+    let mut stats = SweepStats::default();
+    let mut steps = 0.0;
+
+    for i in 0..dots.len() {
+        let a = dots[i].clone();
+
+        if a.state == State::Dead {
+            continue;
+        }
+
+        stats.proposed += 1;
+
+        // log of the hastings correction q(new -> old) / q(old -> new)
+        let log_q;
+        let dx = match scenario.boundary {
+            Boundary::Reject => {
+                let dx = scenario.proposal.sample(rng);
+                if (a.new_pos + dx).norm() >= radius {
+                    continue;
+                }
+                log_q = scenario.proposal.log_ratio(dx);
+                dx
+            }
+            Boundary::Reflect => {
+                let y = a.new_pos + scenario.proposal.sample(rng);
+                let z = match reflect(y, radius) {
+                    Some(z) => z,
+                    None => continue,
+                };
+                log_q = reflected_density(&scenario.proposal, z, a.new_pos, radius).ln()
+                    - reflected_density(&scenario.proposal, a.new_pos, z, radius).ln();
+                z - a.new_pos
+            }
+            Boundary::Truncated => {
+                // the move is rejected when no draw lands inside, for the proposals that can
+                // hardly end in the disk
+                let dx = (0..MAX_DRAWS)
+                    .map(|_| scenario.proposal.sample(rng))
+                    .find(|&dx| (a.new_pos + dx).norm() < radius);
+                let dx = match dx {
+                    Some(dx) => dx,
+                    None => continue,
+                };
+                let norm = normalization.unwrap();
+                log_q = scenario.proposal.log_ratio(dx) + norm.get(a.new_pos).ln()
+                    - norm.get(a.new_pos + dx).ln();
+                dx
+            }
+        };
+
+        // a move proposed where the density rounds to 0 has an undefined correction
+        if !log_q.is_finite() {
+            continue;
+        }
+
+        let mut d_energy = 0.0;
+        for b in dots.iter() {
+            let r1 = (a.new_pos - b.new_pos).norm();
+            let r2 = (a.new_pos + dx - b.new_pos).norm();
+
+            if r1 == 0.0 || r2 == 0.0 {
+                continue;
+            }
+
+            let pot = scenario
+                .interactions
+                .get(a.state.compartment(), b.state.compartment());
+            d_energy += weight(&a, b, scenario) * (pot.energy(r1) - pot.energy(r2));
+        }
+
+        // global potential
+        d_energy += global_potential(a.new_pos, scenario.global_strength)
+            - global_potential(a.new_pos + dx, scenario.global_strength);
+        let kt = temperature * a.mobility;
+        let log_p = if kt > 0.0 {
+            d_energy / kt + log_q
+        } else if d_energy >= 0.0 {
+            0.0
+        } else {
+            f64::NEG_INFINITY
+        };
+        let p = f64::min(log_p.exp(), 1.0);
+
+        if rng.sample(Bernoulli::new(p).unwrap()) {
+            let p = dots[i].new_pos + dx;
+            dots[i].mov(p, t, 0.2);
+            stats.accepted += 1;
+            steps += dx.norm();
+        }
+    }
+
+    if stats.accepted > 0 {
+        stats.mean_step = steps / stats.accepted as f64;
+    }
+    stats
+}
+
+#[test]
+fn equilibrium_density() {
+    use crate::potential::{Interactions, NoInteraction};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::Arc;
+
+    // non interacting dots in the global potential on a small disk, most moves hit the wall
+    let radius = 1.0;
+    let bins = 8;
+
+    // expected probability of each radial bin: integral of exp(-U) over the annulus
+    let strength = 1.0;
+    let mut expected = vec![0.0; bins];
+    let n = 400;
+    for i in 0..n {
+        for j in 0..n {
+            let x = V::new(
+                -radius + 2.0 * radius * (i as f64 + 0.5) / n as f64,
+                -radius + 2.0 * radius * (j as f64 + 0.5) / n as f64,
+            );
+            if x.norm() < radius {
+                let bin = (x.norm() / radius * bins as f64) as usize;
+                expected[bin] += (-global_potential(x, strength)).exp();
+            }
+        }
+    }
+    let total: f64 = expected.iter().sum();
+    for e in expected.iter_mut() {
+        *e /= total;
+    }
+
+    for &boundary in [Boundary::Reject, Boundary::Reflect, Boundary::Truncated].iter() {
+        let scenario = Scenario {
+            interactions: Interactions::uniform(Arc::new(NoInteraction)),
+            proposal: Proposal::Cauchy { scale: 0.3 },
+            boundary,
+            radius,
+            global_strength: strength,
+            ..Scenario::default()
+        };
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut metropolis = Metropolis::new(&scenario);
+        let mut dots: Vec<Dot> = (0..20).map(|_| Dot::new(V::new(0.0, 0.0))).collect();
+
+        let mut counts = vec![0.0; bins];
+        for sweep in 0..6000 {
+            metropolis.sweep(&mut dots, &scenario, 0.0, &mut rng);
+            if sweep >= 100 {
+                for a in dots.iter() {
+                    let bin = (a.new_pos.norm() / radius * bins as f64) as usize;
+                    counts[bin] += 1.0;
+                }
+            }
+        }
+        let total: f64 = counts.iter().sum();
+
+        for (c, e) in counts.iter().zip(expected.iter()) {
+            assert!(
+                (c / total - e).abs() < 0.01,
+                "{:?}: {} instead of {}",
+                boundary,
+                c / total,
+                e
+            );
+        }
+    }
+}
+
+#[test]
+fn truncated_without_inside() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // every step is longer than the diameter, the sweep gives up instead of hanging
+    let scenario = Scenario {
+        radius: 1.0,
+        boundary: Boundary::Truncated,
+        proposal: Proposal::Levy {
+            scale: 3.0,
+            alpha: 1.5,
+        },
+        ..Scenario::default()
+    };
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut dots = vec![Dot::new(V::new(0.0, 0.0)), Dot::new(V::new(0.5, 0.0))];
+    let stats = Metropolis::new(&scenario).sweep(&mut dots, &scenario, 0.0, &mut rng);
+    assert_eq!((stats.proposed, stats.accepted), (2, 0));
+    assert_eq!(dots[1].new_pos, V::new(0.5, 0.0));
+}
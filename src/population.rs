@@ -0,0 +1,251 @@
+use crate::invalid;
+use crate::scenario::Scenario;
+use crate::vec2::V;
+use crate::{Dot, State};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_distr::Uniform;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+/// Which susceptible dots get infected at the start, the seeds take the initial strains in turn.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Seeding {
+    // the initial states are kept as they are
+    None,
+    Random { count: usize },
+    // the dots closest to `center`
+    Cluster { center: V, count: usize },
+    // the dots farthest from the center of the disk
+    Edge { count: usize },
+}
+
+impl Seeding {
+    /// Parses `none`, `random:<n>`, `cluster:<x>:<y>:<n>` or `edge:<n>`.
+    pub fn parse(s: &str) -> Option<Seeding> {
+        let x: Vec<&str> = s.split(':').collect();
+        match x.as_slice() {
+            ["none"] => Some(Seeding::None),
+            ["random", n] => Some(Seeding::Random {
+                count: n.parse().ok()?,
+            }),
+            ["cluster", x, y, n] => Some(Seeding::Cluster {
+                center: V::new(x.parse().ok()?, y.parse().ok()?),
+                count: n.parse().ok()?,
+            }),
+            ["edge", n] => Some(Seeding::Edge {
+                count: n.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub fn seed<R: Rng>(dots: &mut [Dot], seeding: &Seeding, scenario: &Scenario, rng: &mut R) {
+    let mut x: Vec<usize> = (0..dots.len())
+        .filter(|&i| dots[i].state == State::Susceptible)
+        .collect();
+    let count = match *seeding {
+        Seeding::None => 0,
+        Seeding::Random { count } => {
+            x.shuffle(rng);
+            count
+        }
+        Seeding::Cluster { center, count } => {
+            let d = |i: usize| (dots[i].new_pos - center).norm();
+            x.sort_by(|&i, &j| d(i).partial_cmp(&d(j)).unwrap());
+            count
+        }
+        Seeding::Edge { count } => {
+            let d = |i: usize| dots[i].new_pos.norm();
+            x.sort_by(|&i, &j| d(j).partial_cmp(&d(i)).unwrap());
+            count
+        }
+    };
+    for (k, &i) in x.iter().take(count).enumerate() {
+        dots[i].state = State::Asymptomatic(scenario.incubation);
+        dots[i].strain = k % scenario.strains.len();
+    }
+}
+
+/// One dot of an initial condition file, the missing attributes take their default value and
+/// the missing ages are drawn as for the generated dots.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Agent {
+    pub x: f64,
+    pub y: f64,
+    // name of the compartment, the stage starts at time 0
+    pub state: Option<String>,
+    pub age: Option<f64>,
+    pub mobility: Option<f64>,
+    pub strain: Option<usize>,
+}
+
+// csv with a header naming the columns, in any order
+fn parse_csv(text: &str) -> io::Result<Vec<Agent>> {
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    let header: Vec<&str> = match lines.next() {
+        Some(l) => l.split(',').map(|s| s.trim()).collect(),
+        None => return Ok(Vec::new()),
+    };
+    let column = |name: &str| header.iter().position(|&h| h == name);
+    let (x, y) = match (column("x"), column("y")) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return Err(invalid("the columns x and y are needed")),
+    };
+    let (state, age, mobility, strain) = (
+        column("state"),
+        column("age"),
+        column("mobility"),
+        column("strain"),
+    );
+
+    lines
+        .enumerate()
+        .map(|(k, line)| {
+            let f: Vec<&str> = line.split(',').map(|s| s.trim()).collect();
+            let get = |c: Option<usize>| c.and_then(|c| f.get(c)).filter(|s| !s.is_empty());
+            let number = |c: Option<usize>| match get(c) {
+                Some(s) => s
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| invalid(format!("line {}: bad number {}", k + 2, s))),
+                None => Ok(None),
+            };
+            Ok(Agent {
+                x: number(Some(x))?.ok_or_else(|| invalid(format!("line {}: no x", k + 2)))?,
+                y: number(Some(y))?.ok_or_else(|| invalid(format!("line {}: no y", k + 2)))?,
+                state: get(state).map(|s| s.to_string()),
+                age: number(age)?,
+                mobility: number(mobility)?,
+                strain: match get(strain) {
+                    Some(s) => Some(
+                        s.parse()
+                            .map_err(|_| invalid(format!("line {}: bad strain {}", k + 2, s)))?,
+                    ),
+                    None => None,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Reads a json array of agents if the path ends with `.json`, a csv file otherwise.
+pub fn read(path: &str) -> io::Result<Vec<Agent>> {
+    let text = fs::read_to_string(path)?;
+    if path.ends_with(".json") {
+        serde_json::from_str(&text).map_err(invalid)
+    } else {
+        parse_csv(&text)
+    }
+}
+
+pub fn dots<R: Rng>(agents: &[Agent], scenario: &Scenario, rng: &mut R) -> io::Result<Vec<Dot>> {
+    agents
+        .iter()
+        .map(|x| {
+            let r = x.x.hypot(x.y);
+            // the wall itself is outside, as for every mover
+            if !r.is_finite() || r >= scenario.radius {
+                return Err(invalid(format!("({}, {}) is outside the disk", x.x, x.y)));
+            }
+            // a negative mobility would give the square root of a negative diffusion
+            let ok = |v: Option<f64>| v.is_none_or(|v| v >= 0.0 && v.is_finite());
+            if !ok(x.age) || !ok(x.mobility) {
+                return Err(invalid(format!(
+                    "bad age or mobility at ({}, {})",
+                    x.x, x.y
+                )));
+            }
+            let mut a = Dot::new(V::new(x.x, x.y));
+            let age = Uniform::new(0.0, scenario.max_age);
+            a.age = x.age.unwrap_or_else(|| rng.sample(age));
+            a.mobility = x.mobility.unwrap_or(1.0);
+            a.strain = x.strain.unwrap_or(0);
+            if a.strain >= scenario.strains.len() {
+                return Err(invalid(format!("no strain {}", a.strain)));
+            }
+            a.state = match x.state.as_deref() {
+                None | Some("Susceptible") => State::Susceptible,
+                Some("Asymptomatic") => State::Asymptomatic(scenario.incubation),
+                Some("Infected") => State::Infected(scenario.infectious_period),
+                Some("Healed") => State::Healed(scenario.healing_time),
+                Some("Recovered") => {
+                    a.immunity.push(a.strain);
+                    State::Recovered(scenario.waning.sample(rng))
+                }
+                Some("Dead") => State::Dead,
+                Some(s) => return Err(invalid(format!("unknown state {}", s))),
+            };
+            Ok(a)
+        })
+        .collect()
+}
+
+#[test]
+fn initial_conditions() {
+    use crate::strain::Strain;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let scenario = Scenario::default();
+    let agents =
+        parse_csv("y,x,state,age\n0,1,Infected,40\n0,2,,\n0,3,Recovered,\n0,-4,,").unwrap();
+    assert_eq!(agents.len(), 4);
+    assert_eq!(agents[0].x, 1.0);
+    let mut x = dots(&agents, &scenario, &mut rng).unwrap();
+    assert!(matches!(x[0].state, State::Infected(_)));
+    assert_eq!(x[0].age, 40.0);
+    assert_eq!(x[2].immunity, vec![0]);
+    assert!(parse_csv("x,y,strain\n0,0,-3").is_err());
+    assert!(parse_csv("x,y,strain\n0,0,1.7").is_err());
+    let zombie = parse_csv("x,y,state\n0,0,Zombie").unwrap();
+    assert!(dots(&zombie, &scenario, &mut rng).is_err());
+    for bad in [
+        "x,y\n4,4",
+        "x,y\n3,4",
+        "x,y\nNaN,0",
+        "x,y,age\n0,0,inf",
+        "x,y,mobility\n0,0,NaN",
+        "x,y,mobility\n0,0,-1",
+        "x,y,age\n0,0,-5",
+    ]
+    .iter()
+    {
+        assert!(dots(&parse_csv(bad).unwrap(), &scenario, &mut rng).is_err());
+    }
+
+    // only the susceptible dots can be seeded
+    seed(&mut x, &Seeding::Edge { count: 1 }, &scenario, &mut rng);
+    assert!(matches!(x[3].state, State::Asymptomatic(_)));
+    let center = V::new(2.9, 0.0);
+    seed(
+        &mut x,
+        &Seeding::Cluster { center, count: 1 },
+        &scenario,
+        &mut rng,
+    );
+    assert!(matches!(x[1].state, State::Asymptomatic(_)));
+
+    // co-circulating strains from the start
+    let scenario = Scenario {
+        strains: vec![Strain::default(); 2],
+        ..Scenario::default()
+    };
+    let mut x: Vec<Dot> = (0..10).map(|_| Dot::new(V::new(0.0, 0.0))).collect();
+    seed(&mut x, &Seeding::Random { count: 4 }, &scenario, &mut rng);
+    for s in 0..2 {
+        let n = x
+            .iter()
+            .filter(|a| a.state != State::Susceptible && a.strain == s);
+        assert_eq!(n.count(), 2);
+    }
+
+    let cluster = Seeding::Cluster {
+        center: V::new(1.0, -2.0),
+        count: 3,
+    };
+    assert_eq!(Seeding::parse("cluster:1:-2:3"), Some(cluster));
+}
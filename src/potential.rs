@@ -0,0 +1,280 @@
+use crate::vec2::V;
+use crate::Compartment;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+pub trait PairPotential: Send + Sync {
+    fn energy(&self, r: f64) -> f64;
+    // d energy / d r
+    fn derivative(&self, r: f64) -> f64;
+    // description used to save the potential, `None` if it cannot be saved
+    fn save(&self) -> Option<Potential> {
+        None
+    }
+}
+
+/// Saved form of the potentials of this module.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Potential {
+    NoInteraction,
+    LennardJones(LennardJones),
+    SoftSphere(SoftSphere),
+    Yukawa(Yukawa),
+    Morse(Morse),
+}
+
+impl Potential {
+    pub fn build(self) -> Arc<dyn PairPotential> {
+        match self {
+            Potential::NoInteraction => Arc::new(NoInteraction),
+            Potential::LennardJones(x) => Arc::new(x),
+            Potential::SoftSphere(x) => Arc::new(x),
+            Potential::Yukawa(x) => Arc::new(x),
+            Potential::Morse(x) => Arc::new(x),
+        }
+    }
+}
+
+// egg box landscape with minima at odd integer coordinates
+pub fn global_potential(mut x: V, strength: f64) -> f64 {
+    x *= std::f64::consts::PI;
+    strength * (x.0.cos() + x.1.cos())
+}
+
+pub fn global_gradient(mut x: V, strength: f64) -> V {
+    x *= std::f64::consts::PI;
+    -strength * std::f64::consts::PI * V::new(x.0.sin(), x.1.sin())
+}
+
+pub struct NoInteraction;
+
+impl PairPotential for NoInteraction {
+    fn energy(&self, _r: f64) -> f64 {
+        0.0
+    }
+    fn derivative(&self, _r: f64) -> f64 {
+        0.0
+    }
+    fn save(&self) -> Option<Potential> {
+        Some(Potential::NoInteraction)
+    }
+}
+
+// strength * ((d / r)^12 - (d / r)^6)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LennardJones {
+    pub strength: f64,
+    pub d: f64,
+}
+
+impl PairPotential for LennardJones {
+    fn energy(&self, r: f64) -> f64 {
+        let x = (self.d / r).powi(6);
+        self.strength * (x * x - x)
+    }
+    fn derivative(&self, r: f64) -> f64 {
+        let x = (self.d / r).powi(6);
+        -6.0 * self.strength * x * (2.0 * x - 1.0) / r
+    }
+    fn save(&self) -> Option<Potential> {
+        Some(Potential::LennardJones(self.clone()))
+    }
+}
+
+// strength * (d / r)^n
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SoftSphere {
+    pub strength: f64,
+    pub d: f64,
+    pub n: i32,
+}
+
+impl PairPotential for SoftSphere {
+    fn energy(&self, r: f64) -> f64 {
+        self.strength * (self.d / r).powi(self.n)
+    }
+    fn derivative(&self, r: f64) -> f64 {
+        -self.n as f64 * self.energy(r) / r
+    }
+    fn save(&self) -> Option<Potential> {
+        Some(Potential::SoftSphere(self.clone()))
+    }
+}
+
+// strength * (d / r) * exp(-r / length)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Yukawa {
+    pub strength: f64,
+    pub d: f64,
+    pub length: f64,
+}
+
+impl PairPotential for Yukawa {
+    fn energy(&self, r: f64) -> f64 {
+        self.strength * (self.d / r) * (-r / self.length).exp()
+    }
+    fn derivative(&self, r: f64) -> f64 {
+        -self.energy(r) * (1.0 / r + 1.0 / self.length)
+    }
+    fn save(&self) -> Option<Potential> {
+        Some(Potential::Yukawa(self.clone()))
+    }
+}
+
+// depth * ((1 - exp(-(r - r0) / width))^2 - 1)
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Morse {
+    pub depth: f64,
+    pub r0: f64,
+    pub width: f64,
+}
+
+impl PairPotential for Morse {
+    fn energy(&self, r: f64) -> f64 {
+        let x = 1.0 - (-(r - self.r0) / self.width).exp();
+        self.depth * (x * x - 1.0)
+    }
+    fn derivative(&self, r: f64) -> f64 {
+        let x = 1.0 - (-(r - self.r0) / self.width).exp();
+        2.0 * self.depth * x * (1.0 - x) / self.width
+    }
+    fn save(&self) -> Option<Potential> {
+        Some(Potential::Morse(self.clone()))
+    }
+}
+
+/// Symmetric table of pair potentials indexed by the compartments of the two dots.
+#[derive(Clone)]
+pub struct Interactions {
+    table: Vec<Arc<dyn PairPotential>>,
+}
+
+impl Interactions {
+    pub fn uniform(pot: Arc<dyn PairPotential>) -> Interactions {
+        Interactions {
+            table: vec![pot; Compartment::COUNT * Compartment::COUNT],
+        }
+    }
+
+    pub fn get(&self, a: Compartment, b: Compartment) -> &dyn PairPotential {
+        &*self.table[a as usize * Compartment::COUNT + b as usize]
+    }
+
+    pub fn set(&mut self, a: Compartment, b: Compartment, pot: Arc<dyn PairPotential>) {
+        self.table[a as usize * Compartment::COUNT + b as usize] = pot.clone();
+        self.table[b as usize * Compartment::COUNT + a as usize] = pot;
+    }
+
+    /// Sets the potential between `a` and every compartment.
+    pub fn set_row(&mut self, a: Compartment, pot: Arc<dyn PairPotential>) {
+        for &b in Compartment::ALL.iter() {
+            self.set(a, b, pot.clone());
+        }
+    }
+}
+
+// saved as the flat table of potentials
+impl Serialize for Interactions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let table: Option<Vec<Potential>> = self.table.iter().map(|x| x.save()).collect();
+        let table = table.ok_or_else(|| ser::Error::custom("pair potential cannot be saved"))?;
+        table.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interactions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Interactions, D::Error> {
+        let table = Vec::<Potential>::deserialize(deserializer)?;
+        if table.len() != Compartment::COUNT * Compartment::COUNT {
+            return Err(de::Error::invalid_length(
+                table.len(),
+                &"a table per pair of compartments",
+            ));
+        }
+        Ok(Interactions {
+            table: table.into_iter().map(Potential::build).collect(),
+        })
+    }
+}
+
+impl Default for Interactions {
+    // dots gather together, except around the sick ones which are avoided by everybody
+    // (the infected still stick together)
+    fn default() -> Interactions {
+        let attractive: Arc<dyn PairPotential> = Arc::new(LennardJones {
+            strength: 3.0,
+            d: 0.04,
+        });
+        let repulsive: Arc<dyn PairPotential> = Arc::new(SoftSphere {
+            strength: 3.0,
+            d: 0.04,
+            n: 2,
+        });
+
+        let mut x = Interactions::uniform(attractive.clone());
+        x.set_row(Compartment::Infected, repulsive.clone());
+        x.set_row(Compartment::Healed, repulsive);
+        x.set(Compartment::Infected, Compartment::Infected, attractive);
+        x
+    }
+}
+
+#[test]
+fn default_interactions() {
+    let x = Interactions::default();
+    let r = 0.05;
+    let lj = 3.0 * ((0.04f64 / r).powi(12) - (0.04f64 / r).powi(6));
+    let rep = 3.0 * (0.04f64 / r).powi(2);
+
+    let cases = [
+        (Compartment::Susceptible, Compartment::Recovered, lj),
+        (Compartment::Infected, Compartment::Infected, lj),
+        (Compartment::Infected, Compartment::Susceptible, rep),
+        (Compartment::Dead, Compartment::Healed, rep),
+        (Compartment::Healed, Compartment::Infected, rep),
+    ];
+    for &(a, b, e) in cases.iter() {
+        assert!((x.get(a, b).energy(r) - e).abs() < 1e-12);
+        assert!((x.get(b, a).energy(r) - e).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn derivatives() {
+    let pots: Vec<Box<dyn PairPotential>> = vec![
+        Box::new(LennardJones {
+            strength: 3.0,
+            d: 0.04,
+        }),
+        Box::new(SoftSphere {
+            strength: 3.0,
+            d: 0.04,
+            n: 2,
+        }),
+        Box::new(Yukawa {
+            strength: 1.0,
+            d: 0.04,
+            length: 0.2,
+        }),
+        Box::new(Morse {
+            depth: 1.0,
+            r0: 0.05,
+            width: 0.02,
+        }),
+    ];
+    let h = 1e-7;
+    for pot in pots.iter() {
+        for &r in [0.03, 0.045, 0.1, 0.3].iter() {
+            let fd = (pot.energy(r + h) - pot.energy(r - h)) / (2.0 * h);
+            assert!((pot.derivative(r) - fd).abs() < 1e-5 * (1.0 + fd.abs()));
+        }
+    }
+
+    let x = V::new(0.3, -1.2);
+    let g = global_gradient(x, 3.0);
+    let dx = V::new(h, 0.0);
+    let dy = V::new(0.0, h);
+    let gx = (global_potential(x + dx, 3.0) - global_potential(x - dx, 3.0)) / (2.0 * h);
+    let gy = (global_potential(x + dy, 3.0) - global_potential(x - dy, 3.0)) / (2.0 * h);
+    assert!((g.0 - gx).abs() < 1e-5 && (g.1 - gy).abs() < 1e-5);
+}
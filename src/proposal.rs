@@ -0,0 +1,143 @@
+use crate::vec2::V;
+use rand::Rng;
+use rand_distr::{Cauchy, Normal, Uniform};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Distribution of the displacement proposed by the Metropolis step.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Proposal {
+    Gaussian {
+        sigma: f64,
+    },
+    // isotropic direction, cauchy distributed signed length
+    Cauchy {
+        scale: f64,
+    },
+    // isotropic direction, pareto distributed length: P(l > x) = (x / scale)^-alpha for x > scale
+    Levy {
+        scale: f64,
+        alpha: f64,
+    },
+    // `jump` with probability `p_jump`, `local` otherwise
+    Mixture {
+        local: Box<Proposal>,
+        jump: Box<Proposal>,
+        p_jump: f64,
+    },
+}
+
+impl Default for Proposal {
+    fn default() -> Proposal {
+        Proposal::Cauchy { scale: 0.10 }
+    }
+}
+
+fn direction<R: Rng>(rng: &mut R) -> V {
+    let phi = rng.sample(Uniform::new(0.0, 2.0 * PI));
+    V::new(phi.cos(), phi.sin())
+}
+
+impl Proposal {
+    /// Changes the length scale of the steps, of the local moves for a mixture.
+    pub fn set_scale(&mut self, x: f64) {
+        match self {
+            Proposal::Gaussian { sigma } => *sigma = x,
+            Proposal::Cauchy { scale } | Proposal::Levy { scale, .. } => *scale = x,
+            Proposal::Mixture { local, .. } => local.set_scale(x),
+        }
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> V {
+        match self {
+            Proposal::Gaussian { sigma } => {
+                let n = Normal::new(0.0, *sigma).unwrap();
+                V::new(rng.sample(n), rng.sample(n))
+            }
+            Proposal::Cauchy { scale } => {
+                rng.sample(Cauchy::new(0.0, *scale).unwrap()) * direction(rng)
+            }
+            Proposal::Levy { scale, alpha } => {
+                // u < 1 keeps the length above `scale`, where the density is positive
+                let u: f64 = rng.sample(Uniform::new(f64::EPSILON, 1.0));
+                scale * u.powf(-1.0 / alpha) * direction(rng)
+            }
+            Proposal::Mixture {
+                local,
+                jump,
+                p_jump,
+            } => {
+                if rng.gen::<f64>() < *p_jump {
+                    jump.sample(rng)
+                } else {
+                    local.sample(rng)
+                }
+            }
+        }
+    }
+
+    /// Probability density of proposing the displacement `dx`.
+    pub fn density(&self, dx: V) -> f64 {
+        let l = dx.norm();
+        match self {
+            Proposal::Gaussian { sigma } => {
+                (-l * l / (2.0 * sigma * sigma)).exp() / (2.0 * PI * sigma * sigma)
+            }
+            // density of |length| spread over the circle of radius l
+            Proposal::Cauchy { scale } => {
+                2.0 * scale / (PI * (scale * scale + l * l)) / (2.0 * PI * l)
+            }
+            Proposal::Levy { scale, alpha } => {
+                if l < *scale {
+                    0.0
+                } else {
+                    alpha * scale.powf(*alpha) / l.powf(alpha + 1.0) / (2.0 * PI * l)
+                }
+            }
+            Proposal::Mixture {
+                local,
+                jump,
+                p_jump,
+            } => (1.0 - p_jump) * local.density(dx) + p_jump * jump.density(dx),
+        }
+    }
+
+    /// Hastings correction `log q(x + dx -> x) - log q(x -> x + dx)`.
+    pub fn log_ratio(&self, dx: V) -> f64 {
+        self.density(-dx).ln() - self.density(dx).ln()
+    }
+}
+
+#[test]
+fn densities_are_normalized() {
+    let proposals = [
+        Proposal::Gaussian { sigma: 0.3 },
+        Proposal::Cauchy { scale: 0.1 },
+        Proposal::Levy {
+            scale: 0.1,
+            alpha: 1.5,
+        },
+        Proposal::Mixture {
+            local: Box::new(Proposal::Gaussian { sigma: 0.1 }),
+            jump: Box::new(Proposal::Levy {
+                scale: 0.5,
+                alpha: 1.0,
+            }),
+            p_jump: 0.1,
+        },
+    ];
+
+    // integrate over the disk of radius 100 in polar coordinates, with l = exp(s)
+    for prop in proposals.iter() {
+        let n = 200_000;
+        let (s0, s1) = ((1e-6f64).ln(), (100.0f64).ln());
+        let ds = (s1 - s0) / n as f64;
+        let mut total = 0.0;
+        for i in 0..n {
+            let l = (s0 + (i as f64 + 0.5) * ds).exp();
+            total += prop.density(V::new(l, 0.0)) * 2.0 * PI * l * l * ds;
+        }
+        // the cauchy and levy tails beyond l = 100 are below 1e-2
+        assert!(total > 0.98 && total < 1.0 + 1e-3, "{}", total);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::invalid;
+use crate::strain::{Strain, Strains};
+use crate::vec2::V;
+use crate::world::World;
+use crate::{Dot, State};
+use bincode::error::DecodeError;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+
+/// Incremented whenever the recorded events change, older recordings are refused.
+pub const VERSION: u32 = 1;
+// start of the recordings, followed by the version as a little endian u32
+const MAGIC: &[u8; 8] = b"TOMREC\0\0";
+// ticks between two keyframes of the replay
+const KEYFRAME: usize = 50;
+
+/// Actions of the user, kept to be shown during the replay.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Heal { dot: usize },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Event {
+    // the dot goes from `last_pos` at `last_t` to `new_pos` at `new_t`
+    Move {
+        dot: usize,
+        last_pos: V,
+        last_t: f64,
+        new_pos: V,
+        new_t: f64,
+    },
+    Transition {
+        dot: usize,
+        state: State,
+        strain: usize,
+    },
+    // a variant appeared
+    Strain(Strain),
+    Action(Action),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tick {
+    pub t: f64,
+    pub events: Vec<Event>,
+}
+
+// first record of the file
+#[derive(Serialize, Deserialize)]
+struct Header {
+    radius: f64,
+    dots: Vec<Dot>,
+    strains: Strains,
+}
+
+fn write<T: Serialize>(file: &mut BufWriter<File>, x: &T) -> io::Result<()> {
+    bincode::serde::encode_into_std_write(x, file, bincode::config::standard())
+        .map(|_| ())
+        .map_err(invalid)
+}
+
+/// Streams the changes of the world to a file, one tick each time it is recorded.
+pub struct Recorder {
+    file: BufWriter<File>,
+    last: Vec<Dot>,
+    strains: usize,
+    actions: Vec<Action>,
+}
+
+impl Recorder {
+    pub fn create(path: &str, radius: f64, world: &World) -> io::Result<Recorder> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        let header = Header {
+            radius,
+            dots: world.dots.clone(),
+            strains: world.strains.clone(),
+        };
+        write(&mut file, &header)?;
+        Ok(Recorder {
+            file,
+            last: world.dots.clone(),
+            strains: world.strains.len(),
+            actions: Vec::new(),
+        })
+    }
+
+    /// Kept until the next tick.
+    pub fn action(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    /// Writes everything that changed since the last tick.
+    pub fn record(&mut self, t: f64, world: &World) -> io::Result<()> {
+        let mut events: Vec<Event> = world.strains.list[self.strains..]
+            .iter()
+            .map(|s| Event::Strain(s.clone()))
+            .collect();
+        self.strains = world.strains.len();
+        events.extend(self.actions.drain(..).map(Event::Action));
+
+        for (i, (a, b)) in self.last.iter_mut().zip(world.dots.iter()).enumerate() {
+            if a.state != b.state || a.strain != b.strain {
+                events.push(Event::Transition {
+                    dot: i,
+                    state: b.state,
+                    strain: b.strain,
+                });
+            }
+            if a.new_t != b.new_t || a.new_pos != b.new_pos {
+                events.push(Event::Move {
+                    dot: i,
+                    last_pos: b.last_pos,
+                    last_t: b.last_t,
+                    new_pos: b.new_pos,
+                    new_t: b.new_t,
+                });
+            }
+            *a = b.clone();
+        }
+
+        write(&mut self.file, &Tick { t, events })?;
+        self.file.flush()
+    }
+}
+
+fn apply(dots: &mut [Dot], tick: &Tick) {
+    for e in tick.events.iter() {
+        match *e {
+            Event::Move {
+                dot,
+                last_pos,
+                last_t,
+                new_pos,
+                new_t,
+            } => {
+                let a = &mut dots[dot];
+                a.last_pos = last_pos;
+                a.last_t = last_t;
+                a.new_pos = new_pos;
+                a.new_t = new_t;
+            }
+            Event::Transition { dot, state, strain } => {
+                dots[dot].state = state;
+                dots[dot].strain = strain;
+            }
+            Event::Strain(_) | Event::Action(_) => (),
+        }
+    }
+}
+
+/// A recording loaded in memory, the dots can be moved to any time.
+pub struct Replay {
+    pub radius: f64,
+    pub ticks: Vec<Tick>,
+    // every strain of the recording, they only get appended
+    pub strains: Strains,
+    pub dots: Vec<Dot>,
+    // dots before the ticks `0, KEYFRAME, 2 KEYFRAME, ...`
+    keyframes: Vec<Vec<Dot>>,
+    // number of ticks applied to `dots`
+    applied: usize,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> io::Result<Replay> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut magic = [0; 12];
+        file.read_exact(&mut magic)?;
+        if &magic[..8] != MAGIC {
+            return Err(invalid("not a recording"));
+        }
+        let version = u32::from_le_bytes([magic[8], magic[9], magic[10], magic[11]]);
+        if version != VERSION {
+            return Err(invalid(format!(
+                "recording version {} instead of {}",
+                version, VERSION
+            )));
+        }
+
+        let config = bincode::config::standard();
+        let header: Header =
+            bincode::serde::decode_from_std_read(&mut file, config).map_err(invalid)?;
+        let mut strains = header.strains;
+        let mut ticks = Vec::new();
+        loop {
+            let tick = match bincode::serde::decode_from_std_read::<Tick, _, _>(&mut file, config) {
+                Ok(tick) => tick,
+                // a recording interrupted in the middle of a tick is read up to its last
+                // complete tick
+                Err(DecodeError::UnexpectedEnd { .. }) => break,
+                Err(DecodeError::Io { inner, .. })
+                    if inner.kind() == io::ErrorKind::UnexpectedEof =>
+                {
+                    break
+                }
+                Err(e) => return Err(invalid(e)),
+            };
+            for e in tick.events.iter() {
+                if let Event::Strain(s) = e {
+                    strains.list.push(s.clone());
+                }
+            }
+            ticks.push(tick);
+        }
+
+        let mut dots = header.dots;
+        let mut keyframes = Vec::new();
+        for (k, tick) in ticks.iter().enumerate() {
+            if k % KEYFRAME == 0 {
+                keyframes.push(dots.clone());
+            }
+            apply(&mut dots, tick);
+        }
+        let applied = ticks.len();
+
+        Ok(Replay {
+            radius: header.radius,
+            ticks,
+            strains,
+            dots,
+            keyframes,
+            applied,
+        })
+    }
+
+    pub fn start(&self) -> f64 {
+        self.ticks.first().map_or(0.0, |x| x.t)
+    }
+
+    pub fn end(&self) -> f64 {
+        self.ticks.last().map_or(0.0, |x| x.t)
+    }
+
+    /// Moves the dots to time `t` and returns the actions passed on the way forward.
+    pub fn seek(&mut self, t: f64) -> Vec<(f64, Action)> {
+        let target = self.ticks.partition_point(|x| x.t <= t);
+        if target < self.applied || target - self.applied > KEYFRAME {
+            let k = target / KEYFRAME;
+            if let Some(dots) = self.keyframes.get(k) {
+                self.dots = dots.clone();
+                self.applied = k * KEYFRAME;
+            }
+        }
+
+        let mut actions = Vec::new();
+        while self.applied < target {
+            let tick = &self.ticks[self.applied];
+            apply(&mut self.dots, tick);
+            for e in tick.events.iter() {
+                if let Event::Action(x) = e {
+                    actions.push((tick.t, *x));
+                }
+            }
+            self.applied += 1;
+        }
+        actions
+    }
+}
+
+#[test]
+fn replay() {
+    use crate::scenario::Scenario;
+    use crate::snapshot::SimRng;
+    use rand::SeedableRng;
+    use std::fs;
+
+    let scenario = Scenario {
+        radius: 1.0,
+        ..Scenario::default()
+    };
+    let mut rng = SimRng::seed_from_u64(0);
+    let mut world = World::new(&scenario, 30, &mut rng);
+    let path = std::env::temp_dir().join("tom_replay.rec");
+    let path = path.to_str().unwrap();
+
+    let mut recorder = Recorder::create(path, scenario.radius, &world).unwrap();
+    let mut history = vec![world.dots.clone()];
+    for k in 0..120 {
+        let t = k as f64 * 0.3;
+        world.step(&scenario, t, 0.3, &mut rng);
+        recorder.record(t, &world).unwrap();
+        history.push(world.dots.clone());
+    }
+    drop(recorder);
+
+    let mut replay = Replay::load(path).unwrap();
+    assert_eq!(replay.ticks.len(), 120);
+    assert_eq!(replay.strains.len(), world.strains.len());
+    // forwards, backwards across keyframes, then forwards again
+    for &k in [120, 3, 77, 51, 50, 0, 119, 60].iter() {
+        replay.seek(k as f64 * 0.3 - 0.1);
+        for (a, b) in replay.dots.iter().zip(history[k].iter()) {
+            assert!(a.state == b.state && a.new_pos == b.new_pos && a.last_t == b.last_t);
+        }
+    }
+
+    // a truncated last tick is dropped, a corrupt one is an error
+    let mut bytes = fs::read(path).unwrap();
+    let n = bytes.len();
+    bytes.extend_from_slice(&[0; 8]);
+    bytes.push(5);
+    fs::write(path, &bytes).unwrap();
+    assert_eq!(Replay::load(path).unwrap().ticks.len(), 120);
+    bytes.truncate(n);
+    bytes.extend_from_slice(&[0; 8]);
+    bytes.extend_from_slice(&[1, 9]);
+    fs::write(path, &bytes).unwrap();
+    assert!(Replay::load(path).is_err());
+}
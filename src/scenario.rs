@@ -0,0 +1,334 @@
+use crate::aerosol::Aerosol;
+use crate::awareness::{Awareness, Perception};
+use crate::epidemic::{DoseResponse, Transmission, Waning};
+use crate::fomite::Fomite;
+use crate::hospital::Hospital;
+use crate::invalid;
+use crate::montecarlo::Boundary;
+use crate::population::Seeding;
+use crate::potential::Interactions;
+use crate::proposal::Proposal;
+use crate::strain::{Mutation, Strain};
+use crate::treatment::Treatment;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Annealing {
+    Constant,
+    // cools from `start` down to the base temperature in `duration` seconds
+    Linear { start: f64, duration: f64 },
+    Exponential { start: f64, duration: f64 },
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Dynamics {
+    Metropolis,
+    // overdamped langevin integrated in steps of `dt` seconds
+    Langevin { friction: f64, dt: f64 },
+}
+
+/// Every parameter of a simulation, the fields missing from a scenario file keep their default.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    // radius of the disk the dots live in
+    pub radius: f64,
+    pub boundary: Boundary,
+    pub dynamics: Dynamics,
+
+    pub interactions: Interactions,
+    pub global_strength: f64,
+    pub proposal: Proposal,
+
+    // temperature of the metropolis acceptance, multiplied by the mobility of each dot
+    pub temperature: f64,
+    pub annealing: Annealing,
+
+    // fraction of the dots that get `high_mobility` instead of 1
+    pub mobile_fraction: f64,
+    pub high_mobility: f64,
+
+    // dots closer than this can infect each other
+    pub contact_range: f64,
+    pub transmission: Transmission,
+    // durations in seconds
+    pub incubation: f64,
+    pub infectious_period: f64,
+    pub healing_time: f64,
+    // probability to die at the end of the infectious period
+    pub fatality: f64,
+
+    pub hospital: Option<Hospital>,
+    pub treatment: Option<Treatment>,
+    // ages are uniform between 0 and `max_age`
+    pub max_age: f64,
+
+    pub waning: Waning,
+    // fraction of the immunity kept once it has waned
+    pub residual_immunity: f64,
+
+    // strains present at the start, the seeds are spread over them
+    pub strains: Vec<Strain>,
+    pub seeding: Seeding,
+    pub mutation: Mutation,
+    // immunity against the initial strain `j` given by a past infection with the initial strain
+    // `i` at `[i][j]`, none between different initial strains when empty
+    pub cross_immunity: Vec<Vec<f64>>,
+
+    // avoidance of the dots with symptoms driven by the perceived prevalence
+    pub awareness: Option<Awareness>,
+    pub aerosol: Option<Aerosol>,
+    pub fomite: Option<Fomite>,
+}
+
+impl Default for Scenario {
+    fn default() -> Scenario {
+        Scenario {
+            radius: 5.0,
+            boundary: Boundary::Reject,
+            dynamics: Dynamics::Metropolis,
+            interactions: Interactions::default(),
+            global_strength: 3.0,
+            proposal: Proposal::default(),
+            temperature: 1.0,
+            annealing: Annealing::Constant,
+            mobile_fraction: 0.0,
+            high_mobility: 1.0,
+            contact_range: 0.06,
+            transmission: Transmission::Contact,
+            incubation: 5.0,
+            infectious_period: 10.0,
+            healing_time: 5.0,
+            fatality: 0.5,
+            hospital: None,
+            treatment: None,
+            max_age: 90.0,
+            waning: Waning::Never,
+            residual_immunity: 0.0,
+            strains: vec![Strain::default()],
+            seeding: Seeding::Random { count: 1 },
+            mutation: Mutation::default(),
+            cross_immunity: Vec::new(),
+            awareness: None,
+            aerosol: None,
+            fomite: None,
+        }
+    }
+}
+
+fn probability(x: f64) -> bool {
+    (0.0..=1.0).contains(&x)
+}
+
+fn positive(x: f64) -> bool {
+    x > 0.0 && x.is_finite()
+}
+
+fn non_negative(x: f64) -> bool {
+    x >= 0.0 && x.is_finite()
+}
+
+fn response(x: &DoseResponse) -> bool {
+    match *x {
+        DoseResponse::Exponential { scale } => positive(scale),
+        DoseResponse::Hill { d50, n } => positive(d50) && positive(n),
+    }
+}
+
+fn proposal(x: &Proposal) -> bool {
+    match x {
+        Proposal::Gaussian { sigma } => non_negative(*sigma),
+        Proposal::Cauchy { scale } => positive(*scale),
+        Proposal::Levy { scale, alpha } => positive(*scale) && positive(*alpha),
+        Proposal::Mixture {
+            local,
+            jump,
+            p_jump,
+        } => proposal(local) && proposal(jump) && probability(*p_jump),
+    }
+}
+
+impl Scenario {
+    /// Reads a scenario from a json file.
+    pub fn read(path: &str) -> io::Result<Scenario> {
+        let text = fs::read_to_string(path)?;
+        let x: Scenario = serde_json::from_str(&text).map_err(invalid)?;
+        x.validate()?;
+        Ok(x)
+    }
+
+    /// Rejects the values outside the domain of the parameters, which would make a run panic
+    /// or never end.
+    pub fn validate(&self) -> io::Result<()> {
+        let mut checks = vec![
+            ("radius", positive(self.radius)),
+            ("global_strength", self.global_strength.is_finite()),
+            ("proposal", proposal(&self.proposal)),
+            ("temperature", non_negative(self.temperature)),
+            ("mobile_fraction", probability(self.mobile_fraction)),
+            ("high_mobility", non_negative(self.high_mobility)),
+            ("contact_range", non_negative(self.contact_range)),
+            ("incubation", positive(self.incubation)),
+            ("infectious_period", positive(self.infectious_period)),
+            ("healing_time", positive(self.healing_time)),
+            ("fatality", probability(self.fatality)),
+            ("max_age", positive(self.max_age)),
+            ("residual_immunity", probability(self.residual_immunity)),
+            ("strains", !self.strains.is_empty()),
+            (
+                "mutation.probability",
+                probability(self.mutation.probability),
+            ),
+            ("mutation.spread", non_negative(self.mutation.spread)),
+            (
+                "mutation.cross_immunity",
+                probability(self.mutation.cross_immunity),
+            ),
+        ];
+        if !self.cross_immunity.is_empty() {
+            let n = self.strains.len();
+            let row = |x: &Vec<f64>| x.len() == n && x.iter().all(|&x| probability(x));
+            let ok = self.cross_immunity.len() == n && self.cross_immunity.iter().all(row);
+            checks.push(("cross_immunity", ok));
+        }
+        for (k, x) in self.strains.iter().enumerate() {
+            let ok = non_negative(x.transmissibility)
+                && non_negative(x.severity)
+                && x.parent.is_none_or(|p| p < k);
+            checks.push(("strains", ok));
+        }
+        checks.push(match self.dynamics {
+            Dynamics::Metropolis => ("dynamics", true),
+            Dynamics::Langevin { friction, dt } => ("dynamics", positive(friction) && positive(dt)),
+        });
+        checks.push(match self.annealing {
+            Annealing::Constant => ("annealing", true),
+            Annealing::Linear { start, duration } | Annealing::Exponential { start, duration } => {
+                ("annealing", positive(start) && positive(duration))
+            }
+        });
+        checks.push(match &self.transmission {
+            Transmission::Contact => ("transmission", true),
+            Transmission::Dose(x) => ("transmission", response(x)),
+        });
+        checks.push(match self.waning {
+            Waning::Never => ("waning", true),
+            Waning::Fixed(x) | Waning::Exponential { mean: x } => ("waning", positive(x)),
+            Waning::Gamma { mean, shape } => ("waning", positive(mean) && positive(shape)),
+        });
+        if let Some(x) = &self.hospital {
+            checks.push(("hospital.severe_fraction", probability(x.severe_fraction)));
+            checks.push((
+                "hospital.admitted_fatality",
+                probability(x.admitted_fatality),
+            ));
+            checks.push((
+                "hospital.unattended_fatality",
+                probability(x.unattended_fatality),
+            ));
+        }
+        if let Some(x) = &self.treatment {
+            checks.push(("treatment.capacity", non_negative(x.capacity)));
+            checks.push(("treatment.day", positive(x.day)));
+        }
+        if let Some(x) = &self.awareness {
+            let range = match x.perception {
+                Perception::Global => true,
+                Perception::Local { range } => non_negative(range),
+            };
+            checks.push(("awareness.perception", range));
+            checks.push(("awareness.half_prevalence", positive(x.half_prevalence)));
+            checks.push(("awareness.fatigue_time", positive(x.fatigue_time)));
+            checks.push(("awareness.recovery_time", positive(x.recovery_time)));
+        }
+        if let Some(x) = &self.aerosol {
+            checks.push(("aerosol.cell", positive(x.cell)));
+            checks.push(("aerosol.emission", non_negative(x.emission)));
+            checks.push(("aerosol.diffusion", non_negative(x.diffusion)));
+            checks.push(("aerosol.decay", non_negative(x.decay)));
+            checks.push(("aerosol.breathing", non_negative(x.breathing)));
+            checks.push(("aerosol.response", response(&x.response)));
+        }
+        if let Some(x) = &self.fomite {
+            checks.push(("fomite.cell", positive(x.cell)));
+            checks.push(("fomite.shedding", non_negative(x.shedding)));
+            checks.push(("fomite.decay", non_negative(x.decay)));
+            checks.push(("fomite.pickup", non_negative(x.pickup)));
+        }
+        match checks.iter().find(|(_, ok)| !ok) {
+            Some((name, _)) => Err(invalid(format!("bad value of {}", name))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn temperature(&self, t: f64) -> f64 {
+        match self.annealing {
+            Annealing::Constant => self.temperature,
+            Annealing::Linear { start, duration } => {
+                let x = (t / duration).clamp(0.0, 1.0);
+                (1.0 - x) * start + x * self.temperature
+            }
+            Annealing::Exponential { start, duration } => {
+                let x = (t / duration).clamp(0.0, 1.0);
+                start * (self.temperature / start).powf(x)
+            }
+        }
+    }
+}
+
+#[test]
+fn annealing() {
+    let mut s = Scenario {
+        temperature: 0.5,
+        ..Scenario::default()
+    };
+    assert_eq!(s.temperature(3.0), 0.5);
+
+    s.annealing = Annealing::Exponential {
+        start: 2.0,
+        duration: 10.0,
+    };
+    assert!((s.temperature(0.0) - 2.0).abs() < 1e-12);
+    assert!((s.temperature(5.0) - 1.0).abs() < 1e-12);
+    assert!((s.temperature(20.0) - 0.5).abs() < 1e-12);
+
+    s.annealing = Annealing::Linear {
+        start: 2.0,
+        duration: 10.0,
+    };
+    assert!((s.temperature(5.0) - 1.25).abs() < 1e-12);
+}
+
+#[test]
+fn scenario_file() {
+    let path = std::env::temp_dir().join("tom_scenario.json");
+    let text = r#"{"radius": 2.0, "hospital": {"beds": 3}, "dynamics": {"Langevin": {"friction": 1.0, "dt": 0.01}}}"#;
+    fs::write(&path, text).unwrap();
+    let s = Scenario::read(path.to_str().unwrap()).unwrap();
+    assert_eq!(s.radius, 2.0);
+    assert!(matches!(s.dynamics, Dynamics::Langevin { .. }));
+    let hospital = s.hospital.unwrap();
+    assert_eq!(hospital.beds, 3);
+    assert_eq!(
+        hospital.severe_fraction,
+        Hospital::default().severe_fraction
+    );
+    assert_eq!(s.incubation, Scenario::default().incubation);
+
+    let bad = [
+        r#"{"mobile_fraction": 1.5}"#,
+        r#"{"strains": []}"#,
+        r#"{"max_age": 0}"#,
+        r#"{"mutation": {"spread": -1}}"#,
+        r#"{"waning": {"Gamma": {"mean": 10, "shape": 0}}}"#,
+        r#"{"fomite": {"cell": 0}}"#,
+        r#"{"dynamics": {"Langevin": {"friction": 0, "dt": 0.01}}}"#,
+    ];
+    for text in bad.iter() {
+        fs::write(&path, text).unwrap();
+        let e = Scenario::read(path.to_str().unwrap()).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
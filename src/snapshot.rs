@@ -0,0 +1,205 @@
+use crate::invalid;
+use crate::scenario::Scenario;
+use crate::world::World;
+use rand::{Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+
+/// Incremented whenever the saved data changes, older snapshots are refused.
+pub const VERSION: u32 = 2;
+// start of the binary snapshots, followed by the version as a little endian u32
+const MAGIC: &[u8; 8] = b"TOMSNAP\0";
+
+/// Chacha20 generator, as `StdRng`, that remembers its seed so its state can be saved.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "RngState", from = "RngState")]
+pub struct SimRng {
+    seed: [u8; 32],
+    rng: ChaCha20Rng,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RngState {
+    seed: [u8; 32],
+    // number of 32 bits words already generated
+    word_pos: u128,
+}
+
+impl From<SimRng> for RngState {
+    fn from(x: SimRng) -> RngState {
+        RngState {
+            seed: x.seed,
+            word_pos: x.rng.get_word_pos(),
+        }
+    }
+}
+
+impl From<RngState> for SimRng {
+    fn from(x: RngState) -> SimRng {
+        let mut rng = SimRng::from_seed(x.seed);
+        rng.rng.set_word_pos(x.word_pos);
+        rng
+    }
+}
+
+impl SeedableRng for SimRng {
+    type Seed = [u8; 32];
+    fn from_seed(seed: [u8; 32]) -> SimRng {
+        SimRng {
+            seed,
+            rng: ChaCha20Rng::from_seed(seed),
+        }
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+/// Times that can be infinite, saved as `None` since json has no infinity.
+pub mod time {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(x: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        let x = if x.is_finite() { Some(*x) } else { None };
+        x.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::INFINITY))
+    }
+}
+
+/// Complete state of a simulation, enough to resume it exactly.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub t: f64,
+    // time of the next step of the world, so a resumed window takes the same steps
+    pub next_step: f64,
+    pub scenario: Scenario,
+    pub world: World,
+    pub rng: SimRng,
+}
+
+// same fields as `Snapshot`, borrowed
+#[derive(Serialize)]
+struct Saved<'a> {
+    version: u32,
+    t: f64,
+    next_step: f64,
+    scenario: &'a Scenario,
+    world: &'a World,
+    rng: &'a SimRng,
+}
+
+fn check(version: u32) -> io::Result<()> {
+    if version == VERSION {
+        Ok(())
+    } else {
+        Err(invalid(format!(
+            "snapshot version {} instead of {}",
+            version, VERSION
+        )))
+    }
+}
+
+/// Saves as json if the path ends with `.json`, in the compact binary format otherwise.
+pub fn save(
+    path: &str,
+    t: f64,
+    next_step: f64,
+    scenario: &Scenario,
+    world: &World,
+    rng: &SimRng,
+) -> io::Result<()> {
+    let x = Saved {
+        version: VERSION,
+        t,
+        next_step,
+        scenario,
+        world,
+        rng,
+    };
+    let bytes = if path.ends_with(".json") {
+        serde_json::to_vec_pretty(&x).map_err(invalid)?
+    } else {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend(
+            bincode::serde::encode_to_vec(&x, bincode::config::standard()).map_err(invalid)?,
+        );
+        bytes
+    };
+    fs::write(path, bytes)
+}
+
+/// Loads a snapshot in either format.
+pub fn load(path: &str) -> io::Result<Snapshot> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(MAGIC) {
+        let rest = &bytes[MAGIC.len()..];
+        if rest.len() < 4 {
+            return Err(invalid("truncated snapshot"));
+        }
+        check(u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]))?;
+        let (x, _) = bincode::serde::decode_from_slice(&rest[4..], bincode::config::standard())
+            .map_err(invalid)?;
+        Ok(x)
+    } else {
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let x: Version = serde_json::from_slice(&bytes).map_err(invalid)?;
+        check(x.version)?;
+        serde_json::from_slice(&bytes).map_err(invalid)
+    }
+}
+
+#[test]
+fn exact_resume() {
+    let scenario = Scenario {
+        radius: 1.0,
+        ..Scenario::default()
+    };
+    let mut rng = SimRng::seed_from_u64(0);
+    let mut world = World::new(&scenario, 50, &mut rng);
+    for k in 0..20 {
+        world.step(&scenario, k as f64 * 0.3, 0.3, &mut rng);
+    }
+
+    let paths: Vec<String> = ["tom_snapshot.json", "tom_snapshot.bin"]
+        .iter()
+        .map(|x| std::env::temp_dir().join(x).to_str().unwrap().to_string())
+        .collect();
+    for path in paths.iter() {
+        save(path, 6.0, 6.3, &scenario, &world, &rng).unwrap();
+    }
+    for k in 20..40 {
+        world.step(&scenario, k as f64 * 0.3, 0.3, &mut rng);
+    }
+
+    // the restored simulations continue along the same trajectory
+    for path in paths.iter() {
+        let mut x = load(path).unwrap();
+        assert_eq!((x.t, x.next_step), (6.0, 6.3));
+        for k in 20..40 {
+            x.world.step(&x.scenario, k as f64 * 0.3, 0.3, &mut x.rng);
+        }
+        assert!(x.world.dots == world.dots);
+    }
+}
@@ -0,0 +1,148 @@
+use rand::Rng;
+use rand_distr::{Normal, Uniform};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Strain {
+    // multiplies the probability (or dose) of every transmission
+    pub transmissibility: f64,
+    // multiplies the fatality
+    pub severity: f64,
+    pub color: [f32; 3],
+    // strain it mutated from
+    pub parent: Option<usize>,
+}
+
+impl Default for Strain {
+    fn default() -> Strain {
+        Strain {
+            transmissibility: 1.0,
+            severity: 1.0,
+            color: [1.0, 0.0, 0.0],
+            parent: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mutation {
+    // probability for a transmission to produce a new variant
+    pub probability: f64,
+    // standard deviation of the log of the changes of transmissibility and severity
+    pub spread: f64,
+    // immunity against a strain one mutation away, it is raised to the power of the number of
+    // mutations separating two strains and unrelated strains give no immunity
+    pub cross_immunity: f64,
+}
+
+impl Default for Mutation {
+    fn default() -> Mutation {
+        Mutation {
+            probability: 0.0,
+            spread: 0.2,
+            cross_immunity: 0.5,
+        }
+    }
+}
+
+/// Every strain that appeared so far, the variants are appended as they emerge.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Strains {
+    pub list: Vec<Strain>,
+}
+
+impl Strains {
+    pub fn new(initial: &[Strain]) -> Strains {
+        Strains {
+            list: initial.to_vec(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    fn ancestors(&self, mut s: usize) -> Vec<usize> {
+        let mut x = vec![s];
+        while let Some(p) = self.list[s].parent {
+            x.push(p);
+            s = p;
+        }
+        x
+    }
+
+    /// Number of mutations between the two strains, `None` if they are unrelated.
+    pub fn distance(&self, a: usize, b: usize) -> Option<usize> {
+        let aa = self.ancestors(a);
+        let bb = self.ancestors(b);
+        for (i, x) in aa.iter().enumerate() {
+            if let Some(j) = bb.iter().position(|y| y == x) {
+                return Some(i + j);
+            }
+        }
+        None
+    }
+
+    /// Protection against `to` given by a past infection with `from`. Strains descending from
+    /// different initial strains `i` and `j` get `initial[i][j]`, none if it is missing, reduced
+    /// for every mutation away from them.
+    pub fn cross_immunity(
+        &self,
+        from: usize,
+        to: usize,
+        mutation: &Mutation,
+        initial: &[Vec<f64>],
+    ) -> f64 {
+        if let Some(d) = self.distance(from, to) {
+            return mutation.cross_immunity.powi(d as i32);
+        }
+        let (a, b) = (self.ancestors(from), self.ancestors(to));
+        let (i, j) = (a[a.len() - 1], b[b.len() - 1]);
+        let x = initial
+            .get(i)
+            .and_then(|x| x.get(j))
+            .copied()
+            .unwrap_or(0.0);
+        x * mutation.cross_immunity.powi((a.len() + b.len() - 2) as i32)
+    }
+
+    /// Appends a variant of `parent` and returns its index.
+    pub fn mutate<R: Rng>(&mut self, parent: usize, mutation: &Mutation, rng: &mut R) -> usize {
+        let n = Normal::new(0.0, mutation.spread).unwrap();
+        let p = &self.list[parent];
+        let u = Uniform::new(0.2f32, 1.0);
+        let variant = Strain {
+            transmissibility: p.transmissibility * rng.sample(n).exp(),
+            severity: p.severity * rng.sample(n).exp(),
+            color: [rng.sample(u), rng.sample(u), rng.sample(u)],
+            parent: Some(parent),
+        };
+        self.list.push(variant);
+        self.list.len() - 1
+    }
+}
+
+#[test]
+fn cross_immunity() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mutation = Mutation::default();
+    let mut strains = Strains::new(&[Strain::default(), Strain::default()]);
+    let a = strains.mutate(0, &mutation, &mut rng);
+    let b = strains.mutate(a, &mutation, &mut rng);
+    let c = strains.mutate(0, &mutation, &mut rng);
+
+    assert_eq!(strains.distance(b, c), Some(3));
+    assert_eq!(strains.distance(b, 0), Some(2));
+    assert_eq!(strains.distance(1, a), None);
+    assert_eq!(strains.cross_immunity(0, 0, &mutation, &[]), 1.0);
+    assert_eq!(strains.cross_immunity(a, c, &mutation, &[]), 0.25);
+    assert_eq!(strains.cross_immunity(1, b, &mutation, &[]), 0.0);
+    let initial = vec![vec![1.0, 0.8], vec![0.6, 1.0]];
+    assert_eq!(strains.cross_immunity(0, 1, &mutation, &initial), 0.8);
+    assert_eq!(strains.cross_immunity(1, b, &mutation, &initial), 0.15);
+}
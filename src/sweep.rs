@@ -0,0 +1,401 @@
+use crate::ensemble::{ensemble, Ensemble};
+use crate::scenario::Scenario;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_distr::Uniform;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Scenario parameters that can be varied by a sweep.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Parameter {
+    ContactRange,
+    // of every initial strain
+    Transmissibility,
+    ProposalScale,
+    Fatality,
+    Temperature,
+    MobileFraction,
+    Incubation,
+    InfectiousPeriod,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 8] = [
+        Parameter::ContactRange,
+        Parameter::Transmissibility,
+        Parameter::ProposalScale,
+        Parameter::Fatality,
+        Parameter::Temperature,
+        Parameter::MobileFraction,
+        Parameter::Incubation,
+        Parameter::InfectiousPeriod,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Parameter::ContactRange => "contact_range",
+            Parameter::Transmissibility => "transmissibility",
+            Parameter::ProposalScale => "proposal_scale",
+            Parameter::Fatality => "fatality",
+            Parameter::Temperature => "temperature",
+            Parameter::MobileFraction => "mobile_fraction",
+            Parameter::Incubation => "incubation",
+            Parameter::InfectiousPeriod => "infectious_period",
+        }
+    }
+
+    pub fn apply(&self, scenario: &mut Scenario, x: f64) {
+        match self {
+            Parameter::ContactRange => scenario.contact_range = x,
+            Parameter::Transmissibility => {
+                for s in scenario.strains.iter_mut() {
+                    s.transmissibility = x;
+                }
+            }
+            Parameter::ProposalScale => scenario.proposal.set_scale(x),
+            Parameter::Fatality => scenario.fatality = x,
+            Parameter::Temperature => scenario.temperature = x,
+            Parameter::MobileFraction => scenario.mobile_fraction = x,
+            Parameter::Incubation => scenario.incubation = x,
+            Parameter::InfectiousPeriod => scenario.infectious_period = x,
+        }
+    }
+}
+
+/// Interval over which a parameter is varied.
+#[derive(Clone, Copy, Debug)]
+pub struct Range {
+    pub parameter: Parameter,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Range {
+    /// Parses `name:low:high`, with `low <= high`.
+    pub fn parse(s: &str) -> Option<Range> {
+        let x: Vec<&str> = s.split(':').collect();
+        if x.len() != 3 {
+            return None;
+        }
+        let range = Range {
+            parameter: *Parameter::ALL.iter().find(|p| p.name() == x[0])?,
+            low: x[1].parse().ok()?,
+            high: x[2].parse().ok()?,
+        };
+        Some(range).filter(|r| r.low <= r.high && r.high.is_finite() && r.low.is_finite())
+    }
+
+    /// Checks that both ends give a valid scenario, and so every value in between.
+    pub fn check(&self, scenario: &Scenario) -> io::Result<()> {
+        for &x in [self.low, self.high].iter() {
+            let mut s = scenario.clone();
+            self.parameter.apply(&mut s, x);
+            s.validate()?;
+        }
+        Ok(())
+    }
+
+    // `u` in [0, 1]
+    pub fn value(&self, u: f64) -> f64 {
+        self.low + u * (self.high - self.low)
+    }
+}
+
+/// How the points of the unit hypercube are chosen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Design {
+    // every combination of `levels` equally spaced values
+    Grid { levels: usize },
+    LatinHypercube { samples: usize },
+    // one-at-a-time trajectories of elementary effects
+    Morris { trajectories: usize, levels: usize },
+    // Saltelli sampling of two random matrices, needs `samples * (k + 2)` points
+    Sobol { samples: usize },
+}
+
+impl Design {
+    /// Parses `grid:levels`, `lhs:samples`, `morris:trajectories` or `sobol:samples`.
+    pub fn parse(s: &str) -> Option<Design> {
+        let (name, n) = s.split_at(s.find(':')?);
+        let n = n[1..].parse().ok()?;
+        match name {
+            "grid" => Some(Design::Grid { levels: n }),
+            "lhs" => Some(Design::LatinHypercube { samples: n }),
+            "morris" => Some(Design::Morris {
+                trajectories: n,
+                levels: 4,
+            }),
+            "sobol" => Some(Design::Sobol { samples: n }),
+            _ => None,
+        }
+    }
+
+    /// Points of the unit hypercube of dimension `k`.
+    pub fn points<R: Rng>(&self, k: usize, rng: &mut R) -> Vec<Vec<f64>> {
+        let u = Uniform::new(0.0, 1.0);
+        match *self {
+            Design::Grid { levels } => {
+                let level = |i: usize| {
+                    if levels > 1 {
+                        i as f64 / (levels - 1) as f64
+                    } else {
+                        0.5
+                    }
+                };
+                let n = levels.pow(k as u32);
+                (0..n)
+                    .map(|mut i| {
+                        (0..k)
+                            .map(|_| {
+                                let x = level(i % levels);
+                                i /= levels;
+                                x
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+            Design::LatinHypercube { samples } => {
+                let mut x = vec![vec![0.0; k]; samples];
+                for j in 0..k {
+                    let mut strata: Vec<usize> = (0..samples).collect();
+                    strata.shuffle(rng);
+                    for (row, s) in x.iter_mut().zip(strata) {
+                        row[j] = (s as f64 + rng.sample(u)) / samples as f64;
+                    }
+                }
+                x
+            }
+            Design::Morris {
+                trajectories,
+                levels,
+            } => {
+                let delta = morris_delta(levels);
+                let bases = Uniform::new(0, (levels / 2).max(1));
+                let mut x = Vec::new();
+                for _ in 0..trajectories {
+                    let mut p: Vec<f64> = (0..k)
+                        .map(|_| rng.sample(bases) as f64 / (levels - 1).max(1) as f64)
+                        .collect();
+                    let mut order: Vec<usize> = (0..k).collect();
+                    order.shuffle(rng);
+                    x.push(p.clone());
+                    for i in order {
+                        p[i] += delta;
+                        x.push(p.clone());
+                    }
+                }
+                x
+            }
+            Design::Sobol { samples } => {
+                let mut x = Vec::new();
+                for _ in 0..samples {
+                    let a: Vec<f64> = (0..k).map(|_| rng.sample(u)).collect();
+                    let b: Vec<f64> = (0..k).map(|_| rng.sample(u)).collect();
+                    x.push(a.clone());
+                    x.push(b.clone());
+                    for i in 0..k {
+                        let mut ab = a.clone();
+                        ab[i] = b[i];
+                        x.push(ab);
+                    }
+                }
+                x
+            }
+        }
+    }
+}
+
+fn morris_delta(levels: usize) -> f64 {
+    if levels > 1 {
+        levels as f64 / (2 * (levels - 1)) as f64
+    } else {
+        0.5
+    }
+}
+
+pub const OUTCOMES: [&str; 3] = ["final_size", "peak_time", "peak_height"];
+
+/// Outcomes of every seed at one point of the design.
+pub struct Point {
+    pub values: Vec<f64>,
+    pub runs: Vec<(u64, [f64; 3])>,
+}
+
+impl Point {
+    pub fn mean(&self, outcome: usize) -> f64 {
+        let n = self.runs.len().max(1) as f64;
+        self.runs.iter().map(|(_, y)| y[outcome]).sum::<f64>() / n
+    }
+}
+
+/// Runs the ensemble at every point of the design. Every point uses the same seeds, so the
+/// differences between points are not blurred by the noise of the runs.
+pub fn sweep<R: Rng>(
+    scenario: &Scenario,
+    ranges: &[Range],
+    design: &Design,
+    config: &Ensemble,
+    rng: &mut R,
+) -> (Vec<Vec<f64>>, Vec<Point>) {
+    let unit = design.points(ranges.len(), rng);
+    let points = unit
+        .iter()
+        .map(|u| {
+            let mut s = scenario.clone();
+            let values: Vec<f64> = ranges.iter().zip(u).map(|(r, &x)| r.value(x)).collect();
+            for (r, &x) in ranges.iter().zip(values.iter()) {
+                r.parameter.apply(&mut s, x);
+            }
+            let runs = ensemble(&s, config, None)
+                .iter()
+                .map(|x| {
+                    let (t, h) = x.peak();
+                    (x.seed, [x.final_size as f64, t, h as f64])
+                })
+                .collect();
+            Point { values, runs }
+        })
+        .collect();
+    (unit, points)
+}
+
+/// Mean absolute elementary effect and standard deviation of the elementary effects of every
+/// parameter, in units of outcome per full range. `y` follows the points of `Design::Morris`.
+pub fn morris(unit: &[Vec<f64>], y: &[f64], k: usize) -> Vec<(f64, f64)> {
+    let mut effects = vec![Vec::new(); k];
+    for (p, q) in unit.chunks(k + 1).zip(y.chunks(k + 1)) {
+        for s in 1..p.len() {
+            let i = (0..k).find(|&i| p[s][i] != p[s - 1][i]).unwrap();
+            effects[i].push((q[s] - q[s - 1]) / (p[s][i] - p[s - 1][i]));
+        }
+    }
+    effects
+        .iter()
+        .map(|e| {
+            let n = e.len().max(1) as f64;
+            let mu_star = e.iter().map(|x| x.abs()).sum::<f64>() / n;
+            let mu = e.iter().sum::<f64>() / n;
+            let var = e.iter().map(|x| (x - mu).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+            (mu_star, var.sqrt())
+        })
+        .collect()
+}
+
+/// First order and total Sobol indices of every parameter with the estimators of Saltelli
+/// (2010). `y` follows the points of `Design::Sobol`.
+pub fn sobol(y: &[f64], k: usize) -> Vec<(f64, f64)> {
+    let blocks: Vec<&[f64]> = y.chunks(k + 2).collect();
+    let n = blocks.len() as f64;
+    let ab: Vec<f64> = blocks.iter().flat_map(|b| b[..2].to_vec()).collect();
+    let mean = ab.iter().sum::<f64>() / ab.len() as f64;
+    let var = ab.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / ab.len() as f64;
+    if var == 0.0 {
+        return vec![(0.0, 0.0); k];
+    }
+    (0..k)
+        .map(|i| {
+            let first = blocks.iter().map(|b| b[1] * (b[2 + i] - b[0])).sum::<f64>() / n;
+            let total = blocks
+                .iter()
+                .map(|b| (b[0] - b[2 + i]).powi(2))
+                .sum::<f64>()
+                / n
+                / 2.0;
+            (first / var, total / var)
+        })
+        .collect()
+}
+
+/// One line per point and seed.
+pub fn write_results(path: &str, ranges: &[Range], points: &[Point]) -> io::Result<()> {
+    let mut csv = BufWriter::new(File::create(path)?);
+    write!(csv, "point,seed")?;
+    for r in ranges {
+        write!(csv, ",{}", r.parameter.name())?;
+    }
+    writeln!(csv, ",{}", OUTCOMES.join(","))?;
+    for (i, p) in points.iter().enumerate() {
+        for (seed, y) in p.runs.iter() {
+            write!(csv, "{},{}", i, seed)?;
+            for x in p.values.iter() {
+                write!(csv, ",{}", x)?;
+            }
+            writeln!(csv, ",{},{},{}", y[0], y[1], y[2])?;
+        }
+    }
+    csv.flush()
+}
+
+/// Sensitivity indices of the mean outcomes, one line per index, empty for the designs without
+/// indices.
+pub fn write_indices(
+    path: &str,
+    design: &Design,
+    ranges: &[Range],
+    unit: &[Vec<f64>],
+    points: &[Point],
+) -> io::Result<()> {
+    let mut csv = BufWriter::new(File::create(path)?);
+    writeln!(csv, "method,parameter,outcome,index,value")?;
+    let k = ranges.len();
+    for (o, outcome) in OUTCOMES.iter().enumerate() {
+        let y: Vec<f64> = points.iter().map(|p| p.mean(o)).collect();
+        let (method, names, x) = match design {
+            Design::Morris { .. } => ("morris", ["mu_star", "sigma"], morris(unit, &y, k)),
+            Design::Sobol { .. } => ("sobol", ["first_order", "total"], sobol(&y, k)),
+            _ => continue,
+        };
+        for (r, (a, b)) in ranges.iter().zip(x) {
+            let name = r.parameter.name();
+            writeln!(csv, "{},{},{},{},{}", method, name, outcome, names[0], a)?;
+            writeln!(csv, "{},{},{},{},{}", method, name, outcome, names[1], b)?;
+        }
+    }
+    csv.flush()
+}
+
+#[test]
+fn sensitivity_indices() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let f = |x: &Vec<f64>| 4.0 * x[0] + x[1] * x[1];
+
+    let design = Design::Morris {
+        trajectories: 10,
+        levels: 4,
+    };
+    let unit = design.points(3, &mut rng);
+    assert_eq!(unit.len(), 40);
+    let y: Vec<f64> = unit.iter().map(f).collect();
+    let x = morris(&unit, &y, 3);
+    assert!((x[0].0 - 4.0).abs() < 1e-9 && x[0].1 < 1e-9);
+    assert!(x[1].0 > 0.0 && x[1].0 < 2.0);
+    assert_eq!(x[2], (0.0, 0.0));
+
+    // var(4 x0) = 16/12 and var(x1^2) = 4/45
+    let unit = Design::Sobol { samples: 20000 }.points(3, &mut rng);
+    let y: Vec<f64> = unit.iter().map(f).collect();
+    let x = sobol(&y, 3);
+    let s0 = (16.0 / 12.0) / (16.0 / 12.0 + 4.0 / 45.0);
+    assert!((x[0].0 - s0).abs() < 0.05);
+    assert!((x[0].1 - s0).abs() < 0.05);
+    assert!(x[2].0.abs() < 0.02 && x[2].1.abs() < 1e-9);
+
+    let unit = Design::LatinHypercube { samples: 10 }.points(2, &mut rng);
+    for j in 0..2 {
+        let mut strata: Vec<usize> = unit.iter().map(|x| (x[j] * 10.0) as usize).collect();
+        strata.sort();
+        assert_eq!(strata, (0..10).collect::<Vec<_>>());
+    }
+    assert_eq!(Design::Grid { levels: 3 }.points(2, &mut rng).len(), 9);
+
+    assert!(Range::parse("fatality:0.5:0.1").is_none());
+    let x = Range::parse("mobile_fraction:0:2").unwrap();
+    assert!(x.check(&Scenario::default()).is_err());
+    let x = Range::parse("mobile_fraction:0:1").unwrap();
+    assert!(x.check(&Scenario::default()).is_ok());
+}
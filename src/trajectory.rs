@@ -0,0 +1,147 @@
+use crate::invalid;
+use crate::Dot;
+use arrow_array::{Float64Array, RecordBatch, StringArray, UInt32Array};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType, Field, Schema};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+
+/// Positions and states of the dots at the written times, the format follows the extension:
+/// `.xyz`, `.extxyz`, or `.arrow` for an Arrow IPC stream with one row per dot and tick.
+pub enum Trajectory {
+    // the species are the compartments
+    Xyz {
+        file: BufWriter<File>,
+        extended: bool,
+    },
+    Arrow {
+        writer: Box<StreamWriter<BufWriter<File>>>,
+        schema: Arc<Schema>,
+    },
+}
+
+impl Trajectory {
+    pub fn create(path: &str) -> io::Result<Trajectory> {
+        let file = BufWriter::new(File::create(path)?);
+        if path.ends_with(".xyz") || path.ends_with(".extxyz") {
+            return Ok(Trajectory::Xyz {
+                file,
+                extended: path.ends_with(".extxyz"),
+            });
+        }
+        if !path.ends_with(".arrow") {
+            return Err(invalid("trajectory must end with .xyz, .extxyz or .arrow"));
+        }
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("t", DataType::Float64, false),
+            Field::new("dot", DataType::UInt32, false),
+            Field::new("x", DataType::Float64, false),
+            Field::new("y", DataType::Float64, false),
+            Field::new("state", DataType::Utf8, false),
+            Field::new("strain", DataType::UInt32, false),
+        ]));
+        let writer = Box::new(StreamWriter::try_new(file, &schema).map_err(invalid)?);
+        Ok(Trajectory::Arrow { writer, schema })
+    }
+
+    /// Appends a frame, flushed so the file stays readable if the window is closed.
+    pub fn write(&mut self, t: f64, dots: &[Dot]) -> io::Result<()> {
+        match self {
+            Trajectory::Xyz { file, extended } => {
+                writeln!(file, "{}", dots.len())?;
+                if *extended {
+                    writeln!(
+                        file,
+                        "Time={} Properties=species:S:1:pos:R:3:strain:I:1 pbc=\"F F F\"",
+                        t
+                    )?;
+                } else {
+                    writeln!(file, "t={}", t)?;
+                }
+                for a in dots {
+                    let c = a.state.compartment();
+                    let x = a.pos(t);
+                    if *extended {
+                        writeln!(file, "{:?} {} {} 0 {}", c, x.0, x.1, a.strain)?;
+                    } else {
+                        writeln!(file, "{:?} {} {} 0", c, x.0, x.1)?;
+                    }
+                }
+                file.flush()
+            }
+            Trajectory::Arrow { writer, schema } => {
+                let n = dots.len();
+                let batch = RecordBatch::try_new(
+                    schema.clone(),
+                    vec![
+                        Arc::new(Float64Array::from(vec![t; n])),
+                        Arc::new(UInt32Array::from_iter_values(0..n as u32)),
+                        Arc::new(Float64Array::from_iter_values(
+                            dots.iter().map(|a| a.pos(t).0),
+                        )),
+                        Arc::new(Float64Array::from_iter_values(
+                            dots.iter().map(|a| a.pos(t).1),
+                        )),
+                        Arc::new(StringArray::from_iter_values(
+                            dots.iter().map(|a| format!("{:?}", a.state.compartment())),
+                        )),
+                        Arc::new(UInt32Array::from_iter_values(
+                            dots.iter().map(|a| a.strain as u32),
+                        )),
+                    ],
+                )
+                .map_err(invalid)?;
+                writer.write(&batch).map_err(invalid)?;
+                writer.flush().map_err(invalid)
+            }
+        }
+    }
+}
+
+/// Writes a frame if there is a trajectory, a failed write is reported and stops the export.
+pub fn export(trajectory: &mut Option<Trajectory>, t: f64, dots: &[Dot]) {
+    if let Some(x) = trajectory {
+        if let Err(e) = x.write(t, dots) {
+            eprintln!("trajectory: {}", e);
+            *trajectory = None;
+        }
+    }
+}
+
+#[test]
+fn formats() {
+    use crate::vec2::V;
+    use arrow_array::Array;
+    use arrow_ipc::reader::StreamReader;
+
+    let mut dots: Vec<Dot> = (0..3).map(|i| Dot::new(V::new(i as f64, 0.5))).collect();
+    dots[1].state = crate::State::Dead;
+    // halfway to its next position at 0.3
+    dots[2].new_pos = V::new(4.0, 0.5);
+    dots[2].new_t = 0.6;
+    let dir = std::env::temp_dir();
+
+    let path = dir.join("tom_trajectory.extxyz");
+    let mut x = Trajectory::create(path.to_str().unwrap()).unwrap();
+    x.write(0.0, &dots).unwrap();
+    x.write(0.3, &dots).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 10);
+    assert!(lines[6].starts_with("Time=0.3 "));
+    assert_eq!(lines[8], "Dead 1 0.5 0 0");
+    assert_eq!(lines[9], "Susceptible 3 0.5 0 0");
+
+    let path = dir.join("tom_trajectory.arrow");
+    let mut x = Trajectory::create(path.to_str().unwrap()).unwrap();
+    x.write(0.0, &dots).unwrap();
+    x.write(0.3, &dots).unwrap();
+    let reader = StreamReader::try_new(File::open(&path).unwrap(), None).unwrap();
+    let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+    assert_eq!(batches.len(), 2);
+    let state = batches[1].column(4);
+    let state = state.as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(state.value(1), "Dead");
+    assert_eq!(state.len(), 3);
+}
@@ -0,0 +1,134 @@
+use crate::hospital::Care;
+use crate::scenario::Scenario;
+use crate::{Dot, State};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Order in which the infected dots get treated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Priority {
+    // order of the dots in memory
+    Order,
+    // earliest onset of the symptoms first
+    Earliest,
+    Oldest,
+    Random,
+}
+
+/// Automatic treatment of a limited number of infected dots per day.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Treatment {
+    pub capacity: f64,
+    // duration of a day in seconds
+    pub day: f64,
+    pub priority: Priority,
+}
+
+impl Default for Treatment {
+    fn default() -> Treatment {
+        Treatment {
+            capacity: 5.0,
+            day: 1.0,
+            priority: Priority::Earliest,
+        }
+    }
+}
+
+pub fn heal(a: &mut Dot, scenario: &Scenario, t: f64) {
+    a.state = State::Healed(t + scenario.healing_time);
+    a.care = Care::None;
+}
+
+/// The next `n` infected dots to treat.
+pub fn next<R: Rng>(dots: &[Dot], priority: Priority, n: usize, rng: &mut R) -> Vec<usize> {
+    let mut x: Vec<usize> = (0..dots.len())
+        .filter(|&i| matches!(dots[i].state, State::Infected(_)))
+        .collect();
+    match priority {
+        Priority::Order => (),
+        Priority::Earliest => x.sort_by(|&i, &j| {
+            let end = |k: usize| match dots[k].state {
+                State::Infected(end) => end,
+                _ => unreachable!(),
+            };
+            end(i).partial_cmp(&end(j)).unwrap()
+        }),
+        Priority::Oldest => x.sort_by(|&i, &j| dots[j].age.partial_cmp(&dots[i].age).unwrap()),
+        Priority::Random => x.shuffle(rng),
+    }
+    x.truncate(n);
+    x
+}
+
+/// Accumulates the treatment capacity over time and spends it on the infected dots.
+#[derive(Serialize, Deserialize)]
+pub struct Dispenser {
+    credit: f64,
+    pub treated: usize,
+}
+
+impl Dispenser {
+    pub fn new() -> Dispenser {
+        Dispenser {
+            credit: 0.0,
+            treated: 0,
+        }
+    }
+
+    pub fn run<R: Rng>(
+        &mut self,
+        dots: &mut [Dot],
+        scenario: &Scenario,
+        config: &Treatment,
+        t: f64,
+        dt: f64,
+        rng: &mut R,
+    ) {
+        self.credit += config.capacity * dt / config.day;
+        let n = self.credit.floor() as usize;
+        let chosen = next(dots, config.priority, n, rng);
+        // the unused capacity is lost
+        self.credit -= n as f64;
+        for i in chosen {
+            heal(&mut dots[i], scenario, t);
+            self.treated += 1;
+        }
+    }
+}
+
+#[test]
+fn priorities() {
+    use crate::vec2::V;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut dots: Vec<Dot> = (0..5).map(|_| Dot::new(V::new(0.0, 0.0))).collect();
+    let ends = [4.0, 2.0, 3.0, 1.0, 5.0];
+    let ages = [30.0, 80.0, 50.0, 10.0, 60.0];
+    for (i, a) in dots.iter_mut().enumerate() {
+        a.state = State::Infected(ends[i]);
+        a.age = ages[i];
+    }
+    dots[2].state = State::Susceptible;
+
+    assert_eq!(next(&dots, Priority::Order, 2, &mut rng), vec![0, 1]);
+    assert_eq!(next(&dots, Priority::Earliest, 2, &mut rng), vec![3, 1]);
+    assert_eq!(next(&dots, Priority::Oldest, 2, &mut rng), vec![1, 4]);
+    assert_eq!(next(&dots, Priority::Random, 9, &mut rng).len(), 4);
+
+    // 5 per day during 0.5 day, two are treated and the half treatment is lost
+    let scenario = Scenario::default();
+    let mut dispenser = Dispenser::new();
+    dispenser.run(
+        &mut dots,
+        &scenario,
+        &Treatment::default(),
+        0.0,
+        0.5,
+        &mut rng,
+    );
+    assert_eq!(dispenser.treated, 2);
+}
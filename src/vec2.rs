@@ -1,4 +1,6 @@
-#[derive(PartialEq, Clone, Copy)]
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct V(pub f64, pub f64);
 
 impl V {
@@ -0,0 +1,130 @@
+use crate::aerosol::aerosol;
+use crate::awareness;
+use crate::epidemic::epidemic;
+use crate::field::Grid;
+use crate::fomite::fomite;
+use crate::langevin::langevin;
+use crate::montecarlo::{Metropolis, SweepStats};
+use crate::population::seed;
+use crate::scenario::{Dynamics, Scenario};
+use crate::strain::Strains;
+use crate::treatment::Dispenser;
+use crate::vec2::V;
+use crate::{Compartment, Dot, State};
+use rand::Rng;
+use rand_distr::{Bernoulli, Uniform};
+use serde::{Deserialize, Serialize};
+
+/// Everything that evolves during a run, without the window.
+#[derive(Serialize, Deserialize)]
+pub struct World {
+    pub dots: Vec<Dot>,
+    pub strains: Strains,
+    // one layer per strain
+    pub air: Vec<Grid>,
+    pub surfaces: Vec<Grid>,
+    pub dispenser: Dispenser,
+    // cumulative number of onsets of symptoms
+    pub cases: usize,
+    // only depends on the scenario, rebuilt after a restore
+    #[serde(skip)]
+    metropolis: Option<Metropolis>,
+}
+
+impl World {
+    /// `n` dots spread over the disk.
+    pub fn new<R: Rng>(scenario: &Scenario, n: usize, rng: &mut R) -> World {
+        let mut dots = Vec::new();
+        for _ in 0..n {
+            let phi = rng.sample(Uniform::new(0.0, 2.0 * std::f64::consts::PI));
+            let x = rng.sample(Uniform::new(0.0, scenario.radius)) * V::new(phi.cos(), phi.sin());
+            let mut dot = Dot::new(x);
+            dot.age = rng.sample(Uniform::new(0.0, scenario.max_age));
+            if rng.sample(Bernoulli::new(scenario.mobile_fraction).unwrap()) {
+                dot.mobility = scenario.high_mobility;
+            }
+            dots.push(dot);
+        }
+        World::with_dots(scenario, dots, rng)
+    }
+
+    /// Starts from the given dots, seeded according to the scenario.
+    pub fn with_dots<R: Rng>(scenario: &Scenario, mut dots: Vec<Dot>, rng: &mut R) -> World {
+        seed(&mut dots, &scenario.seeding, scenario, rng);
+        World {
+            dots,
+            strains: Strains::new(&scenario.strains),
+            air: Vec::new(),
+            surfaces: Vec::new(),
+            dispenser: Dispenser::new(),
+            cases: 0,
+            metropolis: Some(Metropolis::new(scenario)),
+        }
+    }
+
+    /// Epidemic and motion of the dots during the `dt` seconds that follow `t`.
+    pub fn step<R: Rng>(
+        &mut self,
+        scenario: &Scenario,
+        t: f64,
+        dt: f64,
+        rng: &mut R,
+    ) -> SweepStats {
+        let dots = &mut self.dots;
+        let strains = &mut self.strains;
+
+        if let Some(config) = &scenario.awareness {
+            awareness::update(dots, config, dt);
+        }
+        if let Some(config) = &scenario.treatment {
+            self.dispenser.run(dots, scenario, config, t, dt, rng);
+        }
+        let incubating: Vec<bool> = dots
+            .iter()
+            .map(|a| matches!(a.state, State::Asymptomatic(_)))
+            .collect();
+        epidemic(dots, strains, scenario, t, dt, rng);
+        // the incubation lasts longer than a step, the dots infected just now are still incubating
+        self.cases += dots
+            .iter()
+            .zip(incubating)
+            .filter(|(a, x)| *x && !matches!(a.state, State::Asymptomatic(_)))
+            .count();
+        if let Some(config) = &scenario.aerosol {
+            aerosol(&mut self.air, dots, strains, scenario, config, t, dt, rng);
+        }
+        if let Some(config) = &scenario.fomite {
+            fomite(
+                &mut self.surfaces,
+                dots,
+                strains,
+                scenario,
+                config,
+                t,
+                dt,
+                rng,
+            );
+        }
+
+        match scenario.dynamics {
+            Dynamics::Metropolis => {
+                let metropolis = self
+                    .metropolis
+                    .get_or_insert_with(|| Metropolis::new(scenario));
+                metropolis.sweep(dots, scenario, t, rng)
+            }
+            Dynamics::Langevin { friction, dt: h } => {
+                langevin(dots, scenario, t, dt, friction, h, rng)
+            }
+        }
+    }
+
+    /// Number of dots in each compartment, in the order of `Compartment::ALL`.
+    pub fn counts(&self) -> [usize; Compartment::COUNT] {
+        let mut x = [0; Compartment::COUNT];
+        for a in self.dots.iter() {
+            x[a.state.compartment() as usize] += 1;
+        }
+        x
+    }
+}